@@ -0,0 +1,94 @@
+use crate::{Emulator, QuirkConfig, UnknownOpcodePolicy};
+
+/// Gathers an emulator's configuration in one place before creating it,
+/// e.g. `EmulatorBuilder::new().seed(1234).build()`. Unlike `Emulator::new`,
+/// which uses `QuirkConfig::default`, the builder starts from
+/// `QuirkConfig::cosmac_vip`. Everything else starts as it does for
+/// `Emulator::new`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmulatorBuilder {
+    quirks: QuirkConfig,
+    seed: Option<u64>,
+    strict: bool,
+    unknown_opcode_policy: UnknownOpcodePolicy,
+    history_capacity: usize,
+}
+
+impl Default for EmulatorBuilder {
+    fn default() -> Self {
+        Self {
+            quirks: QuirkConfig::cosmac_vip(),
+            seed: None,
+            strict: false,
+            unknown_opcode_policy: UnknownOpcodePolicy::Error,
+            history_capacity: 0,
+        }
+    }
+}
+
+impl EmulatorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn quirks(mut self, quirks: QuirkConfig) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Makes `RND` deterministic, as `Emulator::with_seed` does.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// See `Emulator::set_strict`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn unknown_opcode_policy(mut self, policy: UnknownOpcodePolicy) -> Self {
+        self.unknown_opcode_policy = policy;
+        self
+    }
+
+    /// Frames of history to keep for `rewind`; 0 (the default) keeps none.
+    pub fn history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    pub fn build(self) -> Emulator {
+        let mut emulator = match self.seed {
+            Some(seed) => Emulator::with_seed(seed),
+            None => Emulator::new(),
+        };
+
+        emulator.set_quirks(self.quirks);
+        emulator.set_strict(self.strict);
+        emulator.set_unknown_opcode_policy(self.unknown_opcode_policy);
+        emulator.set_history_capacity(self.history_capacity);
+        emulator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_uses_the_documented_defaults() {
+        let emu = EmulatorBuilder::new().build();
+
+        assert_eq!(emu.quirks(), QuirkConfig::cosmac_vip());
+        assert_eq!(emu.unknown_opcode_policy, UnknownOpcodePolicy::Error);
+        assert!(!emu.strict);
+        assert_eq!(emu.seed, None);
+        assert_eq!(emu.history_capacity, 0);
+        assert!(emu.opcode_override.is_none());
+        assert!(emu.collision_callback.is_none());
+        assert!(emu.sound_callback.is_none());
+        assert!(emu.trace_callback.is_none());
+    }
+}
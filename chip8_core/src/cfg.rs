@@ -0,0 +1,196 @@
+use crate::disasm::disassemble;
+use crate::{Instruction, START_ADDR};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A run of instructions that always execute together, entered only at the
+/// top and left only after the last instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: u16,
+    pub ops: Vec<u16>,
+}
+
+/// Control-flow graph of the code reachable from `0x200`. Edges run from the
+/// start address of one block to the start address of another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+    pub edges: Vec<(u16, u16)>,
+}
+
+/// Statically walks a ROM from `0x200`, following jumps, calls, returns and
+/// skips, and splits the reachable code into basic blocks. `JP V0, NNN`
+/// depends on a register, so it ends its block with no outgoing edges, as do
+/// `RET`, `EXIT` and targets that fall outside the ROM.
+pub fn control_flow_graph(data: &[u8]) -> Cfg {
+    let rom_end = START_ADDR as usize + data.len();
+    let opcode_at = |addr: u16| {
+        let offset = (addr as usize).checked_sub(START_ADDR as usize)?;
+
+        match (data.get(offset), data.get(offset + 1)) {
+            (Some(&hi), Some(&lo)) => Some(((hi as u16) << 8) | lo as u16),
+            _ => None,
+        }
+    };
+    let in_rom = |addr: u16| addr >= START_ADDR && (addr as usize) + 1 < rom_end;
+
+    // Every reachable instruction and where it can go next
+    let mut successors: BTreeMap<u16, Vec<u16>> = BTreeMap::new();
+    let mut leaders = BTreeSet::from([START_ADDR]);
+    let mut pending = vec![START_ADDR];
+
+    while let Some(addr) = pending.pop() {
+        if successors.contains_key(&addr) {
+            continue;
+        }
+
+        let next: Vec<u16> = match opcode_at(addr) {
+            Some(op) => next_addrs(addr, op)
+                .into_iter()
+                .filter(|&a| in_rom(a))
+                .collect(),
+            None => continue,
+        };
+
+        if next != [addr.wrapping_add(2)] {
+            leaders.extend(&next);
+        }
+
+        pending.extend(&next);
+        successors.insert(addr, next);
+    }
+
+    let mut blocks = Vec::new();
+    let mut edges = Vec::new();
+
+    for &start in leaders.iter().filter(|addr| successors.contains_key(addr)) {
+        let mut ops = Vec::new();
+        let mut addr = start;
+
+        loop {
+            ops.push(opcode_at(addr).unwrap());
+
+            let next = &successors[&addr];
+            let falls_through = next.len() == 1 && next[0] == addr.wrapping_add(2);
+
+            if !falls_through || leaders.contains(&next[0]) {
+                edges.extend(next.iter().map(|&to| (start, to)));
+                break;
+            }
+
+            addr = next[0];
+        }
+
+        blocks.push(BasicBlock { start, ops });
+    }
+
+    Cfg { blocks, edges }
+}
+
+// Addresses that can run after the instruction `op` at `addr`
+fn next_addrs(addr: u16, op: u16) -> Vec<u16> {
+    let next = addr.wrapping_add(2);
+
+    match Instruction::decode(op) {
+        Instruction::Ret | Instruction::Exit | Instruction::JumpOffset { .. } => vec![],
+        Instruction::Jump { nnn } => vec![nnn],
+        Instruction::Call { nnn } => vec![nnn, next],
+        Instruction::SkipEqByte { .. }
+        | Instruction::SkipNotEqByte { .. }
+        | Instruction::SkipEqReg { .. }
+        | Instruction::SkipNotEqReg { .. }
+        | Instruction::SkipKeyPressed { .. }
+        | Instruction::SkipKeyNotPressed { .. } => vec![next, next.wrapping_add(2)],
+        _ => vec![next],
+    }
+}
+
+impl Cfg {
+    /// Renders the graph in Graphviz DOT format, one box per block listing
+    /// its disassembly.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph cfg {\n    node [shape=box fontname=monospace];\n");
+
+        for block in &self.blocks {
+            let lines: Vec<String> = block
+                .ops
+                .iter()
+                .enumerate()
+                .map(|(i, &op)| {
+                    let addr = block.start as usize + i * 2;
+                    format!("0x{:03X}  {}\\l", addr, disassemble(op))
+                })
+                .collect();
+
+            dot.push_str(&format!(
+                "    b{:03X} [label=\"{}\"];\n",
+                block.start,
+                lines.concat()
+            ));
+        }
+
+        for (from, to) in &self.edges {
+            dot.push_str(&format!("    b{:03X} -> b{:03X};\n", from, to));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0x200: LD V0, 0; SE V0, 1; JP 0x20A; LD V1, 5; EXIT
+    // 0x20A: CALL 0x210; JP 0x20C; (data) 0x210: RET
+    const ROM: [u8; 18] = [
+        0x60, 0x00, 0x30, 0x01, 0x12, 0x0A, 0x61, 0x05, 0x00, 0xFD, 0x22, 0x10, 0x12, 0x0C, 0xAB,
+        0xCD, 0x00, 0xEE,
+    ];
+
+    fn block(start: u16, ops: &[u16]) -> BasicBlock {
+        BasicBlock {
+            start,
+            ops: ops.to_vec(),
+        }
+    }
+
+    #[test]
+    fn splits_blocks_at_branches() {
+        let cfg = control_flow_graph(&ROM);
+
+        assert_eq!(
+            cfg.blocks,
+            [
+                block(0x200, &[0x6000, 0x3001]),
+                block(0x204, &[0x120A]),
+                block(0x206, &[0x6105, 0x00FD]),
+                block(0x20A, &[0x2210]),
+                block(0x20C, &[0x120C]),
+                block(0x210, &[0x00EE]),
+            ]
+        );
+        assert_eq!(
+            cfg.edges,
+            [
+                (0x200, 0x204),
+                (0x200, 0x206),
+                (0x204, 0x20A),
+                (0x20A, 0x210),
+                (0x20A, 0x20C),
+                (0x20C, 0x20C),
+            ]
+        );
+    }
+
+    #[test]
+    fn dot_lists_blocks_and_edges() {
+        let dot = control_flow_graph(&ROM).to_dot();
+
+        assert!(dot.starts_with("digraph cfg {"));
+        assert!(dot.contains("b206 [label=\"0x206  LD V1, 0x05\\l0x208  EXIT\\l\"];"));
+        assert!(dot.contains("b20A -> b210;"));
+        assert!(!dot.contains("b20E"));
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::Instant;
+
+/// A monotonic time source for `Emulator::advance_time`, so the timers can
+/// be paced by whatever clock the platform has: `Instant` on desktop,
+/// `performance.now()` in a browser or a hardware timer on a microcontroller.
+pub trait Clock {
+    /// Microseconds since some fixed point. Only differences matter.
+    fn now_micros(&self) -> u64;
+}
+
+/// `Clock` backed by `std::time::Instant`.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now_micros(&self) -> u64 {
+        self.origin.elapsed().as_micros() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Emulator;
+    use std::cell::Cell;
+
+    struct MockClock(Cell<u64>);
+
+    impl MockClock {
+        fn advance_ms(&self, ms: u64) {
+            self.0.set(self.0.get() + ms * 1_000);
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now_micros(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn timers_follow_the_injected_clock() {
+        let clock = MockClock(Cell::new(5_000_000));
+        let mut emu = Emulator::new();
+
+        // LD V0, 30; LD DT, V0
+        emu.run_program(&[0x601E, 0xF015]).unwrap();
+        assert_eq!(emu.advance_time(&clock), 0);
+
+        clock.advance_ms(100);
+        assert_eq!(emu.advance_time(&clock), 6);
+        assert_eq!(emu.delay_timer(), 24);
+
+        // 108ms in total is still only 6 whole ticks
+        clock.advance_ms(8);
+        assert_eq!(emu.advance_time(&clock), 0);
+
+        // A stall of over a second is skipped rather than replayed
+        clock.advance_ms(3_000);
+        assert_eq!(emu.advance_time(&clock), 0);
+        assert_eq!(emu.delay_timer(), 24);
+
+        clock.advance_ms(50);
+        assert_eq!(emu.advance_time(&clock), 3);
+        assert_eq!(emu.delay_timer(), 21);
+    }
+}
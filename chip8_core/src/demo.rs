@@ -0,0 +1,132 @@
+use crate::{Chip8Error, Emulator, NUM_KEYS};
+
+/// A recorded play session: the RNG seed, how many frames it lasts, and the
+/// keypad state (bit N set means key N is held) from each frame where it
+/// changed. Replaying it on the same ROM always ends in the same state.
+///
+/// The text form has one entry per line, with `#` starting a comment:
+///
+/// ```text
+/// seed 1234
+/// frames 600
+/// 0 0000
+/// 30 0020
+/// 45 0000
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Demo {
+    pub seed: u64,
+    pub frames: u64,
+    pub inputs: Vec<(u64, u16)>,
+}
+
+impl Demo {
+    pub fn parse(text: &str) -> Result<Demo, String> {
+        let mut demo = Demo {
+            seed: 0,
+            frames: 0,
+            inputs: Vec::new(),
+        };
+
+        for (num, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let err = |msg: &str| format!("line {}: {}", num + 1, msg);
+
+            match words.as_slice() {
+                [] => (),
+                ["seed", seed] => demo.seed = seed.parse().map_err(|_| err("bad seed"))?,
+                ["frames", frames] => {
+                    demo.frames = frames.parse().map_err(|_| err("bad frame count"))?
+                }
+                [frame, keys] => {
+                    let frame = frame.parse().map_err(|_| err("bad frame number"))?;
+                    let keys = u16::from_str_radix(keys, 16).map_err(|_| err("bad key mask"))?;
+
+                    demo.inputs.push((frame, keys));
+                }
+                _ => return Err(err("expected `seed N`, `frames N` or `FRAME KEYS`")),
+            }
+        }
+
+        demo.inputs.sort_by_key(|(frame, _)| *frame);
+        Ok(demo)
+    }
+
+    /// Runs `rom` from power-on with this demo's seed and input, stepping
+    /// `ticks_per_frame` instructions per frame, and returns the emulator in
+    /// its final state.
+    pub fn replay(&self, rom: &[u8], ticks_per_frame: usize) -> Result<Emulator, Chip8Error> {
+        let mut emu = Emulator::with_seed(self.seed);
+        let mut inputs = self.inputs.iter().peekable();
+
+        emu.load(rom)?;
+
+        for frame in 0..self.frames {
+            while let Some((_, keys)) = inputs.next_if(|(at, _)| *at <= frame) {
+                for key in 0..NUM_KEYS {
+                    emu.keypress(key, keys & (1 << key) != 0);
+                }
+            }
+
+            // A program that exits just sits there until the demo ends
+            for _ in 0..ticks_per_frame {
+                if emu.is_halted() {
+                    break;
+                }
+
+                emu.tick()?;
+            }
+
+            emu.tick_timers();
+        }
+
+        Ok(emu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LD V0, K; LD F, V0; DRW V1, V2, 5; ADD V1, 5; then wait for the key
+    // to be released: SKP V0; JP 0x200; JP 0x208
+    const ROM: [u8; 14] = [
+        0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x25, 0x71, 0x05, 0xE0, 0x9E, 0x12, 0x00, 0x12, 0x08,
+    ];
+
+    const DEMO: &str = "\
+        seed 1
+        frames 30
+        2 0008  # 3
+        6 0000
+        12 0400 # A
+        15 0000
+    ";
+
+    #[test]
+    fn replay_ends_on_the_recorded_screen() {
+        let emu = Demo::parse(DEMO).unwrap().replay(&ROM, 10).unwrap();
+        let ascii = emu.screen_to_ascii();
+        let rows: Vec<&str> = ascii.lines().map(str::trim_end).collect();
+
+        assert_eq!(
+            rows[..6],
+            [
+                "████ ████",
+                "   █ █  █",
+                "████ ████",
+                "   █ █  █",
+                "████ █  █",
+                "",
+            ]
+        );
+        assert_eq!(emu.screen_hash(), 0x1be6_fae4_840c_541f);
+    }
+
+    #[test]
+    fn parse_rejects_bad_lines() {
+        assert_eq!(Demo::parse("seed x"), Err("line 1: bad seed".to_string()));
+        assert!(Demo::parse("1 2 3").is_err());
+    }
+}
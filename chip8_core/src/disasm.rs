@@ -0,0 +1,140 @@
+use crate::Instruction;
+use std::fmt;
+
+/// An instruction split into its mnemonic and the operand fields it uses.
+/// Fields the instruction doesn't use are `None`, so `LD VX, DT` has an `x`
+/// but no `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    pub op: u16,
+    pub mnemonic: &'static str,
+    pub x: Option<u8>,
+    pub y: Option<u8>,
+    pub n: Option<u8>,
+    pub nn: Option<u8>,
+    pub nnn: Option<u16>,
+}
+
+/// Decodes `op` into a `DecodedInstruction`. Unknown opcodes get the
+/// mnemonic `DB` and no operands.
+pub fn decode(op: u16) -> DecodedInstruction {
+    let instruction = Instruction::decode(op);
+    let mut decoded = DecodedInstruction {
+        op,
+        mnemonic: instruction.mnemonic(),
+        x: None,
+        y: None,
+        n: None,
+        nn: None,
+        nnn: None,
+    };
+
+    // Only the fields each instruction reads
+    match instruction {
+        Instruction::ScrollDown { n } | Instruction::Debug { n } => decoded.n = Some(n),
+        Instruction::Jump { nnn }
+        | Instruction::Call { nnn }
+        | Instruction::LoadIndex { nnn }
+        | Instruction::JumpOffset { nnn, .. } => decoded.nnn = Some(nnn),
+        Instruction::SkipEqByte { x, nn }
+        | Instruction::SkipNotEqByte { x, nn }
+        | Instruction::LoadByte { x, nn }
+        | Instruction::AddByte { x, nn }
+        | Instruction::Random { x, nn } => {
+            decoded.x = Some(x);
+            decoded.nn = Some(nn);
+        }
+        Instruction::SkipEqReg { x, y }
+        | Instruction::LoadReg { x, y }
+        | Instruction::Or { x, y }
+        | Instruction::And { x, y }
+        | Instruction::Xor { x, y }
+        | Instruction::AddReg { x, y }
+        | Instruction::Sub { x, y }
+        | Instruction::SubN { x, y }
+        | Instruction::SkipNotEqReg { x, y } => {
+            decoded.x = Some(x);
+            decoded.y = Some(y);
+        }
+        Instruction::Draw { x, y, n } => {
+            decoded.x = Some(x);
+            decoded.y = Some(y);
+            decoded.n = Some(n);
+        }
+        Instruction::ShiftRight { x, .. }
+        | Instruction::ShiftLeft { x, .. }
+        | Instruction::SkipKeyPressed { x }
+        | Instruction::SkipKeyNotPressed { x }
+        | Instruction::LoadFromDelay { x }
+        | Instruction::WaitKey { x }
+        | Instruction::LoadDelay { x }
+        | Instruction::LoadSound { x }
+        | Instruction::AddIndex { x }
+        | Instruction::LoadFont { x }
+        | Instruction::Bcd { x }
+        | Instruction::StoreRegs { x }
+        | Instruction::LoadRegs { x } => decoded.x = Some(x),
+        _ => (),
+    }
+
+    decoded
+}
+
+impl fmt::Display for DecodedInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Instruction::decode(self.op).fmt(f)
+    }
+}
+
+/// Whether `op` is an instruction this interpreter understands.
+pub fn is_known(op: u16) -> bool {
+    !matches!(Instruction::decode(op), Instruction::Unknown(_))
+}
+
+pub fn disassemble(op: u16) -> String {
+    Instruction::decode(op).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Emulator;
+
+    #[test]
+    fn decodes_drw_operands() {
+        let decoded = decode(0xDA3F);
+
+        assert_eq!(decoded.mnemonic, "DRW");
+        assert_eq!(
+            (decoded.x, decoded.y, decoded.n),
+            (Some(0xA), Some(3), Some(0xF))
+        );
+        assert_eq!((decoded.nn, decoded.nnn), (None, None));
+        assert_eq!(decoded.to_string(), "DRW VA, V3, 15");
+    }
+
+    #[test]
+    fn current_instruction_reads_at_the_pc() {
+        let mut emu = Emulator::new();
+        // LD V0, 1; DRW V1, V2, 5
+        emu.load(&[0x60, 0x01, 0xD1, 0x25]).unwrap();
+        emu.tick().unwrap();
+
+        let decoded = emu.current_instruction().unwrap();
+        assert_eq!(decoded.op, 0xD125);
+        assert_eq!(
+            (decoded.x, decoded.y, decoded.n),
+            (Some(1), Some(2), Some(5))
+        );
+    }
+
+    #[test]
+    fn unknown_opcodes_have_no_operands() {
+        let decoded = decode(0xE000);
+
+        assert_eq!(decoded.mnemonic, "DB");
+        assert_eq!(decoded.x, None);
+        assert!(!is_known(0xE000));
+        assert_eq!(disassemble(0xE000), "DB 0xE000");
+    }
+}
@@ -0,0 +1,81 @@
+/// Read-only view of the screen at its current resolution, so callers don't
+/// have to do the row-major index math themselves.
+#[derive(Debug, Clone, Copy)]
+pub struct Display<'a> {
+    pixels: &'a [bool],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Display<'a> {
+    pub(crate) fn new(pixels: &'a [bool], width: usize, height: usize) -> Self {
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the pixel at `(x, y)` is lit. Anything off screen is unlit.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[x + self.width * y]
+    }
+
+    /// Coordinates of every lit pixel, row by row.
+    pub fn lit_pixels(&self) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let width = self.width;
+
+        self.pixels
+            .iter()
+            .enumerate()
+            .filter(|(_, &lit)| lit)
+            .map(move |(i, _)| (i % width, i / width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Emulator;
+
+    #[test]
+    fn core_reports_logical_resolution_only() {
+        let mut emu = Emulator::new();
+
+        for (op, size) in [(0x00FE, (64, 32)), (0x00FF, (128, 64))] {
+            emu.run_program(&[op]).unwrap();
+            let display = emu.display();
+
+            assert_eq!(emu.display_size(), size);
+            assert_eq!((display.width(), display.height()), size);
+            assert_eq!(emu.get_display().len(), size.0 * size.1);
+            assert_eq!(
+                emu.framebuffer_rgba([255; 4], [0; 4]).len(),
+                size.0 * size.1 * 4
+            );
+        }
+    }
+
+    #[test]
+    fn lit_pixels_are_in_logical_coordinates() {
+        let mut emu = Emulator::new();
+        emu.load_into_ram(&[0xC0], 0x300).unwrap();
+        emu.set_register(0, 126);
+        emu.set_register(1, 63);
+
+        // HIGH; LD I, 0x300; DRW V0, V1, 1
+        emu.run_program(&[0x00FF, 0xA300, 0xD011]).unwrap();
+
+        let lit: Vec<_> = emu.display().lit_pixels().collect();
+        assert_eq!(lit, [(126, 63), (127, 63)]);
+        assert!(emu.display().get(127, 63));
+        assert!(!emu.display().get(128, 63));
+    }
+}
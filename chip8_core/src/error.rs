@@ -0,0 +1,60 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    ProtectedWrite { addr: u16, pc: u16 },
+    MemoryOutOfBounds { addr: usize, pc: u16 },
+    InvalidAddress { addr: u16 },
+    UnknownOpcode { op: u16, pc: u16 },
+    RomTooLarge { size: usize, max: usize },
+    StackOverflow { pc: u16 },
+    StackUnderflow { pc: u16 },
+    PcOutOfBounds { pc: u16 },
+    Halted { pc: u16 },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::ProtectedWrite { addr, pc } => write!(
+                f,
+                "Instruction at 0x{:03X} tried to write to protected address 0x{:03X}",
+                pc, addr
+            ),
+            Chip8Error::MemoryOutOfBounds { addr, pc } => write!(
+                f,
+                "Instruction at 0x{:03X} accessed 0x{:X}, past the end of RAM",
+                pc, addr
+            ),
+            Chip8Error::InvalidAddress { addr } => {
+                write!(f, "Address 0x{:X} is outside of RAM", addr)
+            }
+            Chip8Error::UnknownOpcode { op, pc } => {
+                write!(f, "Unknown opcode 0x{:04X} at 0x{:03X}", op, pc)
+            }
+            Chip8Error::StackOverflow { pc } => write!(
+                f,
+                "Instruction at 0x{:03X} called a subroutine with the stack already full",
+                pc
+            ),
+            Chip8Error::StackUnderflow { pc } => write!(
+                f,
+                "Instruction at 0x{:03X} returned with an empty stack",
+                pc
+            ),
+            Chip8Error::PcOutOfBounds { pc } => {
+                write!(f, "Program counter 0x{:X} ran off the end of RAM", pc)
+            }
+            Chip8Error::Halted { pc } => {
+                write!(f, "Program has exited with 00FD (PC 0x{:03X})", pc)
+            }
+            Chip8Error::RomTooLarge { size, max } => write!(
+                f,
+                "ROM is {} bytes, but at most {} bytes fit in RAM",
+                size, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
@@ -0,0 +1,70 @@
+use crate::Emulator;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` its output is fixed forever, so
+/// hashes can be stored and compared across builds and platforms.
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub(crate) fn new() -> Self {
+        Fnv1a(FNV_OFFSET_BASIS)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Stable hash of a ROM image, suitable for keying per-ROM data on disk.
+pub fn rom_hash(data: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// Runs `data` for `cycles` instructions from a fixed RNG seed with no input
+/// and hashes the resulting machine state, timing frames as `run_cycles`
+/// does. Two interpreters with the same quirks should agree on this value
+/// for any ROM. Oversized ROMs are truncated to fit, and execution stops
+/// early if the ROM faults.
+pub fn behavior_fingerprint(data: &[u8], cycles: usize, seed: u64) -> u64 {
+    let mut emu = Emulator::with_seed(seed);
+    emu.load_truncating(data);
+
+    // A fault just ends the run early; the state at that point is hashed
+    let _ = emu.run_cycles(cycles);
+
+    emu.state_hash()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RND V0, 0xFF; LD F, V0; DRW V1, V2, 5; ADD V1, 5; JP 0x200
+    const ROM: [u8; 10] = [0xC0, 0xFF, 0xF0, 0x29, 0xD1, 0x25, 0x71, 0x05, 0x12, 0x00];
+
+    #[test]
+    fn rom_hash_is_fnv1a() {
+        assert_eq!(rom_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(rom_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        let fingerprint = behavior_fingerprint(&ROM, 500, 7);
+
+        assert_eq!(behavior_fingerprint(&ROM, 500, 7), fingerprint);
+        assert_ne!(behavior_fingerprint(&ROM, 500, 8), fingerprint);
+        assert_ne!(behavior_fingerprint(&ROM, 499, 7), fingerprint);
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt;
+
+/// One decoded instruction, as returned by `Emulator::step`. Register
+/// operands are register numbers, not their values. Variants are named after
+/// their mnemonics, with a suffix where one mnemonic has several forms.
+///
+/// This is the only opcode decoder: the interpreter, the disassembler and
+/// the static analysis all go through `Instruction::decode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// `0000`
+    Nop,
+    /// `00E0`
+    Cls,
+    /// `00EE`
+    Ret,
+    /// `00CN`
+    ScrollDown { n: u8 },
+    /// `00FB`
+    ScrollRight,
+    /// `00FC`
+    ScrollLeft,
+    /// `00FD`
+    Exit,
+    /// `00FE`
+    LowRes,
+    /// `00FF`
+    HighRes,
+    /// `0FFN`
+    Debug { n: u8 },
+    /// `1NNN`
+    Jump { nnn: u16 },
+    /// `2NNN`
+    Call { nnn: u16 },
+    /// `3XNN`
+    SkipEqByte { x: u8, nn: u8 },
+    /// `4XNN`
+    SkipNotEqByte { x: u8, nn: u8 },
+    /// `5XY0`
+    SkipEqReg { x: u8, y: u8 },
+    /// `6XNN`
+    LoadByte { x: u8, nn: u8 },
+    /// `7XNN`
+    AddByte { x: u8, nn: u8 },
+    /// `8XY0`
+    LoadReg { x: u8, y: u8 },
+    /// `8XY1`
+    Or { x: u8, y: u8 },
+    /// `8XY2`
+    And { x: u8, y: u8 },
+    /// `8XY3`
+    Xor { x: u8, y: u8 },
+    /// `8XY4`
+    AddReg { x: u8, y: u8 },
+    /// `8XY5`
+    Sub { x: u8, y: u8 },
+    /// `8XY6`
+    ShiftRight { x: u8, y: u8 },
+    /// `8XY7`
+    SubN { x: u8, y: u8 },
+    /// `8XYE`
+    ShiftLeft { x: u8, y: u8 },
+    /// `9XY0`
+    SkipNotEqReg { x: u8, y: u8 },
+    /// `ANNN`
+    LoadIndex { nnn: u16 },
+    /// `BNNN`
+    JumpOffset { x: u8, nnn: u16 },
+    /// `CXNN`
+    Random { x: u8, nn: u8 },
+    /// `DXYN`, or a 16x16 sprite for `DXY0` in high resolution
+    Draw { x: u8, y: u8, n: u8 },
+    /// `EX9E`
+    SkipKeyPressed { x: u8 },
+    /// `EXA1`
+    SkipKeyNotPressed { x: u8 },
+    /// `FX07`
+    LoadFromDelay { x: u8 },
+    /// `FX0A`
+    WaitKey { x: u8 },
+    /// `FX15`
+    LoadDelay { x: u8 },
+    /// `FX18`
+    LoadSound { x: u8 },
+    /// `FX1E`
+    AddIndex { x: u8 },
+    /// `FX29`
+    LoadFont { x: u8 },
+    /// `FX33`
+    Bcd { x: u8 },
+    /// `FX55`
+    StoreRegs { x: u8 },
+    /// `FX65`
+    LoadRegs { x: u8 },
+    /// Anything else, with the raw opcode.
+    Unknown(u16),
+}
+
+impl Instruction {
+    pub fn decode(op: u16) -> Instruction {
+        let first_digit = (op & 0xF000) >> 12;
+        let x = ((op & 0x0F00) >> 8) as u8;
+        let y = ((op & 0x00F0) >> 4) as u8;
+        let n = (op & 0x000F) as u8;
+
+        let nnn = op & 0xFFF;
+        let nn = (op & 0xFF) as u8;
+
+        match (first_digit, x, y, n) {
+            (0, 0, 0, 0) => Instruction::Nop,
+            (0, 0, 0xE, 0) => Instruction::Cls,
+            (0, 0, 0xE, 0xE) => Instruction::Ret,
+            (0, 0, 0xC, _) => Instruction::ScrollDown { n },
+            (0, 0, 0xF, 0xB) => Instruction::ScrollRight,
+            (0, 0, 0xF, 0xC) => Instruction::ScrollLeft,
+            (0, 0, 0xF, 0xD) => Instruction::Exit,
+            (0, 0, 0xF, 0xE) => Instruction::LowRes,
+            (0, 0, 0xF, 0xF) => Instruction::HighRes,
+            (0, 0xF, 0xF, _) => Instruction::Debug { n },
+            (1, _, _, _) => Instruction::Jump { nnn },
+            (2, _, _, _) => Instruction::Call { nnn },
+            (3, _, _, _) => Instruction::SkipEqByte { x, nn },
+            (4, _, _, _) => Instruction::SkipNotEqByte { x, nn },
+            (5, _, _, _) => Instruction::SkipEqReg { x, y },
+            (6, _, _, _) => Instruction::LoadByte { x, nn },
+            (7, _, _, _) => Instruction::AddByte { x, nn },
+            (8, _, _, 0) => Instruction::LoadReg { x, y },
+            (8, _, _, 1) => Instruction::Or { x, y },
+            (8, _, _, 2) => Instruction::And { x, y },
+            (8, _, _, 3) => Instruction::Xor { x, y },
+            (8, _, _, 4) => Instruction::AddReg { x, y },
+            (8, _, _, 5) => Instruction::Sub { x, y },
+            (8, _, _, 6) => Instruction::ShiftRight { x, y },
+            (8, _, _, 7) => Instruction::SubN { x, y },
+            (8, _, _, 0xE) => Instruction::ShiftLeft { x, y },
+            (9, _, _, 0) => Instruction::SkipNotEqReg { x, y },
+            (0xA, _, _, _) => Instruction::LoadIndex { nnn },
+            (0xB, _, _, _) => Instruction::JumpOffset { x, nnn },
+            (0xC, _, _, _) => Instruction::Random { x, nn },
+            (0xD, _, _, _) => Instruction::Draw { x, y, n },
+            (0xE, _, 9, 0xE) => Instruction::SkipKeyPressed { x },
+            (0xE, _, 0xA, 1) => Instruction::SkipKeyNotPressed { x },
+            (0xF, _, 0, 7) => Instruction::LoadFromDelay { x },
+            (0xF, _, 0, 0xA) => Instruction::WaitKey { x },
+            (0xF, _, 1, 5) => Instruction::LoadDelay { x },
+            (0xF, _, 1, 8) => Instruction::LoadSound { x },
+            (0xF, _, 1, 0xE) => Instruction::AddIndex { x },
+            (0xF, _, 2, 9) => Instruction::LoadFont { x },
+            (0xF, _, 3, 3) => Instruction::Bcd { x },
+            (0xF, _, 5, 5) => Instruction::StoreRegs { x },
+            (0xF, _, 6, 5) => Instruction::LoadRegs { x },
+            _ => Instruction::Unknown(op),
+        }
+    }
+    /// The assembler mnemonic, e.g. `LD` for every load form. Unknown
+    /// opcodes are `DB`, as they would be written out as raw data.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Nop => "NOP",
+            Instruction::Cls => "CLS",
+            Instruction::Ret => "RET",
+            Instruction::ScrollDown { .. } => "SCD",
+            Instruction::ScrollRight => "SCR",
+            Instruction::ScrollLeft => "SCL",
+            Instruction::Exit => "EXIT",
+            Instruction::LowRes => "LOW",
+            Instruction::HighRes => "HIGH",
+            Instruction::Debug { .. } => "DBG",
+            Instruction::Jump { .. } | Instruction::JumpOffset { .. } => "JP",
+            Instruction::Call { .. } => "CALL",
+            Instruction::SkipEqByte { .. } | Instruction::SkipEqReg { .. } => "SE",
+            Instruction::SkipNotEqByte { .. } | Instruction::SkipNotEqReg { .. } => "SNE",
+            Instruction::AddByte { .. }
+            | Instruction::AddReg { .. }
+            | Instruction::AddIndex { .. } => "ADD",
+            Instruction::Or { .. } => "OR",
+            Instruction::And { .. } => "AND",
+            Instruction::Xor { .. } => "XOR",
+            Instruction::Sub { .. } => "SUB",
+            Instruction::ShiftRight { .. } => "SHR",
+            Instruction::SubN { .. } => "SUBN",
+            Instruction::ShiftLeft { .. } => "SHL",
+            Instruction::Random { .. } => "RND",
+            Instruction::Draw { .. } => "DRW",
+            Instruction::SkipKeyPressed { .. } => "SKP",
+            Instruction::SkipKeyNotPressed { .. } => "SKNP",
+            Instruction::LoadByte { .. }
+            | Instruction::LoadReg { .. }
+            | Instruction::LoadIndex { .. }
+            | Instruction::LoadFromDelay { .. }
+            | Instruction::WaitKey { .. }
+            | Instruction::LoadDelay { .. }
+            | Instruction::LoadSound { .. }
+            | Instruction::LoadFont { .. }
+            | Instruction::Bcd { .. }
+            | Instruction::StoreRegs { .. }
+            | Instruction::LoadRegs { .. } => "LD",
+            Instruction::Unknown(_) => "DB",
+        }
+    }
+}
+
+/// Formats the instruction as assembly, e.g. `DRW V0, V1, 5`.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mnemonic = self.mnemonic();
+
+        match *self {
+            Instruction::ScrollDown { n } => write!(f, "{} {}", mnemonic, n),
+            Instruction::Debug { n } => write!(f, "{} V{:X}", mnemonic, n),
+            Instruction::Jump { nnn } | Instruction::Call { nnn } => {
+                write!(f, "{} 0x{:03X}", mnemonic, nnn)
+            }
+            Instruction::SkipEqByte { x, nn }
+            | Instruction::SkipNotEqByte { x, nn }
+            | Instruction::LoadByte { x, nn }
+            | Instruction::AddByte { x, nn }
+            | Instruction::Random { x, nn } => write!(f, "{} V{:X}, 0x{:02X}", mnemonic, x, nn),
+            Instruction::SkipEqReg { x, y }
+            | Instruction::LoadReg { x, y }
+            | Instruction::Or { x, y }
+            | Instruction::And { x, y }
+            | Instruction::Xor { x, y }
+            | Instruction::AddReg { x, y }
+            | Instruction::Sub { x, y }
+            | Instruction::SubN { x, y }
+            | Instruction::SkipNotEqReg { x, y } => write!(f, "{} V{:X}, V{:X}", mnemonic, x, y),
+            Instruction::ShiftRight { x, .. }
+            | Instruction::ShiftLeft { x, .. }
+            | Instruction::SkipKeyPressed { x }
+            | Instruction::SkipKeyNotPressed { x } => write!(f, "{} V{:X}", mnemonic, x),
+            Instruction::LoadIndex { nnn } => write!(f, "LD I, 0x{:03X}", nnn),
+            Instruction::JumpOffset { nnn, .. } => write!(f, "JP V0, 0x{:03X}", nnn),
+            Instruction::Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::LoadFromDelay { x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey { x } => write!(f, "LD V{:X}, K", x),
+            Instruction::LoadDelay { x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::LoadSound { x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddIndex { x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::LoadFont { x } => write!(f, "LD F, V{:X}", x),
+            Instruction::Bcd { x } => write!(f, "LD B, V{:X}", x),
+            Instruction::StoreRegs { x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::LoadRegs { x } => write!(f, "LD V{:X}, [I]", x),
+            Instruction::Unknown(op) => write!(f, "DB 0x{:04X}", op),
+            _ => f.write_str(mnemonic),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm;
+
+    #[test]
+    fn formats_as_assembly() {
+        assert_eq!(Instruction::decode(0x00E0).to_string(), "CLS");
+        assert_eq!(Instruction::decode(0xB2A0).to_string(), "JP V0, 0x2A0");
+        assert_eq!(Instruction::decode(0xD125).to_string(), "DRW V1, V2, 5");
+        assert_eq!(Instruction::decode(0xF365).to_string(), "LD V3, [I]");
+        assert_eq!(Instruction::decode(0xE000).to_string(), "DB 0xE000");
+    }
+
+    #[test]
+    fn disassembler_agrees_with_the_decoder() {
+        for op in 0..=u16::MAX {
+            let instruction = Instruction::decode(op);
+            let decoded = disasm::decode(op);
+
+            assert_eq!(disasm::disassemble(op), instruction.to_string());
+            assert_eq!(decoded.to_string(), instruction.to_string());
+            assert_eq!(decoded.mnemonic, instruction.mnemonic());
+            assert_eq!(
+                disasm::is_known(op),
+                !matches!(instruction, Instruction::Unknown(_))
+            );
+        }
+    }
+}
@@ -1,14 +1,54 @@
-use rand::random;
+use rng::RngSource;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::time::Duration;
+
+mod builder;
+mod cfg;
+mod clock;
+mod demo;
+pub mod disasm;
+mod display;
+mod error;
+mod fingerprint;
+mod input;
+mod instruction;
+mod quirks;
+pub mod rng;
+mod state;
+mod timing;
+mod variant;
+
+pub use builder::EmulatorBuilder;
+pub use cfg::{control_flow_graph, BasicBlock, Cfg};
+pub use clock::{Clock, SystemClock};
+pub use demo::Demo;
+pub use display::Display;
+pub use error::Chip8Error;
+pub use fingerprint::{behavior_fingerprint, rom_hash};
+pub use input::{InputEvent, InputRecorder};
+pub use instruction::Instruction;
+pub use quirks::{DisplayWait, DrawMode, MemoryIncrement, QuirkConfig};
+pub use state::{EmulatorState, RegisterFile, SaveState, StateDiff};
+pub use timing::VIP_CYCLES_PER_FRAME;
+pub use variant::{detect_variant, Chip8Variant};
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+/// Size of the SUPER-CHIP high-resolution display enabled by `00FF`.
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
 
 const START_ADDR: u16 = 0x200;
-const RAM_SIZE: usize = 4096;
+pub const RAM_SIZE: usize = 4096;
 const REGISTER_COUNT: usize = 16;
 const STACK_SIZE: usize = 16;
 const NUM_KEYS: usize = 16;
 const FONTSET_SIZE: usize = 80;
+const TIMER_HZ: u64 = 60;
+// Instructions per 60Hz frame in run_cycles, matching the desktop default
+const HEADLESS_TICKS_PER_FRAME: u64 = 10;
+// Longest stall advance_time will catch up on, in timer ticks
+const MAX_CATCH_UP: u64 = TIMER_HZ;
 
 const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -29,17 +69,63 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+/// What `tick` does with a word that isn't a known instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownOpcodePolicy {
+    /// Abort the process.
+    Panic,
+    /// Skip the word as if it were a NOP.
+    Nop,
+    /// Fail with `Chip8Error::UnknownOpcode`, leaving the PC past the word.
+    /// This is the default.
+    Error,
+}
+
+pub type OpcodeOverride = Box<dyn FnMut(&mut Emulator, u16) -> bool>;
+pub type CollisionCallback = Box<dyn FnMut(u16)>;
+pub type TraceCallback = Box<dyn FnMut(u16, u16)>;
+pub type SoundCallback = Box<dyn FnMut(bool)>;
+
 pub struct Emulator {
     pc: u16,
     ram: [u8; RAM_SIZE],
-    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    screen: Vec<bool>,
+    hires: bool,
+    display_dirty: bool,
     v_reg: [u8; REGISTER_COUNT],
     i_reg: u16,
     stack_ptr: u16,
     stack: [u16; STACK_SIZE],
     keys: [bool; NUM_KEYS],
+    waiting_for_key: Option<u8>,
+    // Key seen going down during `LD VX, K` with wait_for_key_release on
+    pressed_while_waiting: Option<u8>,
+    awaiting_vblank: bool,
+    vblank_released: bool,
+    drawn_this_frame: bool,
+    halted: bool,
     delay_timer: u8,
     sound_timer: u8,
+    protected: Option<(u16, u16)>,
+    strict: bool,
+    unknown_opcode_policy: UnknownOpcodePolicy,
+    unknown_opcodes: BTreeMap<u16, u64>,
+    breakpoints: BTreeSet<u16>,
+    cycle_count: u64,
+    vip_cycles: u64,
+    timer_ticks: u64,
+    // Clock reading and timer tick count the last time advance_time synced
+    clock_base: Option<(u64, u64)>,
+    quirks: QuirkConfig,
+    rng: RngSource,
+    seed: Option<u64>,
+    opcode_override: Option<OpcodeOverride>,
+    collision_callback: Option<CollisionCallback>,
+    sound_callback: Option<SoundCallback>,
+    trace_callback: Option<TraceCallback>,
+    trace_range: Option<(u16, u16)>,
+    history: VecDeque<EmulatorState>,
+    history_capacity: usize,
 }
 
 impl Default for Emulator {
@@ -47,14 +133,41 @@ impl Default for Emulator {
         Self {
             pc: START_ADDR,
             ram: [0; RAM_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            screen: vec![false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            hires: false,
+            display_dirty: true,
             v_reg: [0; REGISTER_COUNT],
             i_reg: 0,
             stack_ptr: 0,
             stack: [0; STACK_SIZE],
             keys: [false; NUM_KEYS],
+            waiting_for_key: None,
+            pressed_while_waiting: None,
+            awaiting_vblank: false,
+            vblank_released: false,
+            drawn_this_frame: false,
+            halted: false,
             delay_timer: 0,
             sound_timer: 0,
+            protected: None,
+            strict: false,
+            unknown_opcode_policy: UnknownOpcodePolicy::Error,
+            unknown_opcodes: BTreeMap::new(),
+            breakpoints: BTreeSet::new(),
+            cycle_count: 0,
+            vip_cycles: 0,
+            timer_ticks: 0,
+            clock_base: None,
+            quirks: QuirkConfig::default(),
+            rng: RngSource::from_entropy(),
+            seed: None,
+            opcode_override: None,
+            collision_callback: None,
+            sound_callback: None,
+            trace_callback: None,
+            trace_range: None,
+            history: VecDeque::new(),
+            history_capacity: 0,
         }
     }
 }
@@ -66,402 +179,2511 @@ impl Emulator {
         emulator
     }
 
-    pub fn reset(&mut self) {
-        self.pc = START_ADDR;
-        self.ram = [0; RAM_SIZE];
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
-        self.v_reg = [0; REGISTER_COUNT];
-        self.i_reg = 0;
-        self.stack_ptr = 0;
-        self.stack = [0; STACK_SIZE];
-        self.keys = [false; NUM_KEYS];
-        self.delay_timer = 0;
-        self.sound_timer = 0;
+    /// Creates an emulator whose `RND` results are fully determined by
+    /// `seed`, including after a `reset`. Seeded emulators use the built-in
+    /// `rng::Xorshift32`, so results match with or without the `rand` feature.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut emulator = Emulator::new();
+        emulator.seed = Some(seed);
+        emulator.rng = RngSource::seeded(seed);
+        emulator
+    }
 
-        self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+    /// Starts configuring an emulator with several settings at once. See
+    /// `EmulatorBuilder`.
+    pub fn builder() -> EmulatorBuilder {
+        EmulatorBuilder::new()
     }
 
-    pub fn tick(&mut self) {
-        let op = self.fetch();
-        self.execute(op);
+    /// Creates an emulator with `quirks` already applied.
+    pub fn with_quirks(quirks: QuirkConfig) -> Self {
+        let mut emulator = Emulator::new();
+        emulator.quirks = quirks;
+        emulator
     }
 
-    pub fn tick_timers(&mut self) {
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
+    /// Returns the machine to its power-on state. Everything except
+    /// host-side configuration (quirks, strict mode, write protection,
+    /// breakpoints, hooks, history capacity and the RNG seed) is rebuilt from
+    /// scratch, so any display mode or other state added later is covered
+    /// too. Recorded history is discarded.
+    pub fn reset(&mut self) {
+        let protected = self.protected;
+        let strict = self.strict;
+        let unknown_opcode_policy = self.unknown_opcode_policy;
+        let breakpoints = std::mem::take(&mut self.breakpoints);
+        let quirks = self.quirks;
+        let opcode_override = self.opcode_override.take();
+        let collision_callback = self.collision_callback.take();
+        let sound_callback = self.sound_callback.take();
+        let was_sounding = self.is_sound_active();
+        let trace_callback = self.trace_callback.take();
+        let trace_range = self.trace_range;
+        let history_capacity = self.history_capacity;
+
+        *self = match self.seed {
+            Some(seed) => Emulator::with_seed(seed),
+            None => Emulator::new(),
+        };
+        self.protected = protected;
+        self.strict = strict;
+        self.unknown_opcode_policy = unknown_opcode_policy;
+        self.breakpoints = breakpoints;
+        self.quirks = quirks;
+        self.opcode_override = opcode_override;
+        self.collision_callback = collision_callback;
+        self.sound_callback = sound_callback;
+        self.trace_callback = trace_callback;
+        self.trace_range = trace_range;
+        self.history_capacity = history_capacity;
+
+        if was_sounding {
+            self.notify_sound(false);
         }
+    }
 
-        if self.sound_timer > 0 {
-            if self.sound_timer == 1 {
-                // BEEP
-            }
+    pub fn quirks(&self) -> QuirkConfig {
+        self.quirks
+    }
 
-            self.sound_timer -= 1;
-        }
+    pub fn set_quirks(&mut self, quirks: QuirkConfig) {
+        self.quirks = quirks;
     }
 
-    pub fn get_display(&self) -> &[bool] {
-        &self.screen
+    /// Installs a hook that sees every opcode before it executes. If the hook
+    /// returns `true` the opcode counts as handled and the built-in behaviour
+    /// is skipped entirely, including any PC changes beyond the normal fetch.
+    /// The hook has full mutable access to the emulator, so it can patch
+    /// around ROM bugs but can just as easily break a program.
+    pub fn set_opcode_override(&mut self, hook: OpcodeOverride) {
+        self.opcode_override = Some(hook);
     }
 
-    pub fn keypress(&mut self, idx: usize, pressed: bool) {
-        self.keys[idx] = pressed;
+    pub fn clear_opcode_override(&mut self) {
+        self.opcode_override = None;
     }
 
-    pub fn load(&mut self, data: &[u8]) {
-        let start = START_ADDR as usize;
-        let end = (START_ADDR as usize) + data.len();
+    /// Installs a callback that runs whenever `DRW` sets VF because a sprite
+    /// collided with a lit pixel, receiving the address of that `DRW`.
+    pub fn set_collision_callback(&mut self, callback: CollisionCallback) {
+        self.collision_callback = Some(callback);
+    }
 
-        self.ram[start..end].copy_from_slice(data);
+    pub fn clear_collision_callback(&mut self) {
+        self.collision_callback = None;
     }
 
-    fn fetch(&mut self) -> u16 {
-        let higher_byte = self.ram[self.pc as usize] as u16;
-        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
-        let op = (higher_byte << 8) | lower_byte;
+    /// Installs a callback that runs only when the buzzer starts (`true`) or
+    /// stops (`false`), i.e. when the sound timer goes from zero to non-zero
+    /// or back. That happens in `tick_timers` when the timer runs out, on
+    /// `LD ST, VX`, and on anything that replaces the machine's state.
+    pub fn set_sound_callback(&mut self, callback: SoundCallback) {
+        self.sound_callback = Some(callback);
+    }
 
-        self.pc += 2;
-        op
+    pub fn clear_sound_callback(&mut self) {
+        self.sound_callback = None;
     }
 
-    fn push(&mut self, val: u16) {
-        self.stack[self.stack_ptr as usize] = val;
-        self.stack_ptr += 1;
+    /// Installs a callback that sees every instruction just before it runs,
+    /// as `(pc, opcode)`.
+    pub fn set_trace_callback(&mut self, callback: TraceCallback) {
+        self.trace_callback = Some(callback);
     }
 
-    fn pop(&mut self) -> u16 {
-        self.stack_ptr -= 1;
-        self.stack[self.stack_ptr as usize]
+    pub fn clear_trace_callback(&mut self) {
+        self.trace_callback = None;
     }
 
-    // Instructions
+    /// Limits tracing to instructions at `start..end`, so a single routine
+    /// can be followed without wading through the rest of the program.
+    pub fn set_trace_range(&mut self, start: u16, end: u16) {
+        self.trace_range = Some((start, end));
+    }
 
-    fn clear_screen(&mut self) {
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+    pub fn clear_trace_range(&mut self) {
+        self.trace_range = None;
     }
 
-    fn end_subroutine(&mut self) {
-        let ret_addr = self.pop();
-        self.pc = ret_addr;
+    pub fn tick(&mut self) -> Result<(), Chip8Error> {
+        self.step().map(|_| ())
     }
 
-    fn jump(&mut self, nnn: u16) {
-        self.pc = nnn;
+    /// Runs exactly one instruction, like `tick`, and returns what it was.
+    /// Once the program has exited with `00FD` this fails with
+    /// `Chip8Error::Halted` and leaves the machine untouched.
+    pub fn step(&mut self) -> Result<Instruction, Chip8Error> {
+        if self.halted {
+            return Err(Chip8Error::Halted { pc: self.pc });
+        }
+
+        let pc = self.pc;
+        let op = self.fetch()?;
+
+        if let Some(callback) = self.trace_callback.as_mut() {
+            let in_range = match self.trace_range {
+                Some((start, end)) => pc >= start && pc < end,
+                None => true,
+            };
+
+            if in_range {
+                callback(pc, op);
+            }
+        }
+        let instruction = Instruction::decode(op);
+        let x_coord = match instruction {
+            Instruction::Draw { x, .. } => self.v_reg[x as usize],
+            _ => 0,
+        };
+
+        self.vip_cycles += timing::vip_cost(instruction, x_coord);
+        self.execute(op)?;
+
+        self.cycle_count += 1;
+        Ok(instruction)
     }
 
-    fn call_subroutine(&mut self, nnn: u16) {
-        self.push(self.pc);
-        self.pc = nnn;
+    /// Runs `n` instructions without a frontend, calling `tick_timers` after
+    /// every 10th instruction since power-on to stand in for the 60Hz frame.
+    /// Stops at the first error, e.g. to check that a test ROM fails where
+    /// it should.
+    pub fn run_cycles(&mut self, n: usize) -> Result<(), Chip8Error> {
+        for _ in 0..n {
+            self.tick()?;
+
+            if self.cycle_count.is_multiple_of(HEADLESS_TICKS_PER_FRAME) {
+                self.tick_timers();
+            }
+        }
+
+        Ok(())
     }
 
-    fn skip_if_vx_equals_nn(&mut self, second_digit: u16, nn: u16) {
-        let x = second_digit as usize;
-        let nn = nn as u8;
+    /// Ticks until `cond` holds or `max_cycles` instructions have run,
+    /// returning whether the condition was met. The condition is checked
+    /// before every instruction, so it is never skipped over.
+    pub fn run_until<F: Fn(&Emulator) -> bool>(
+        &mut self,
+        cond: F,
+        max_cycles: usize,
+    ) -> Result<bool, Chip8Error> {
+        for _ in 0..max_cycles {
+            if cond(self) {
+                return Ok(true);
+            }
 
-        if self.v_reg[x] == nn {
-            self.pc += 2;
+            self.tick()?;
         }
+
+        Ok(cond(self))
     }
 
-    fn skip_if_vx_not_equals_nn(&mut self, second_digit: u16, nn: u16) {
-        let x = second_digit as usize;
-        let nn = nn as u8;
+    /// Writes `ops` to RAM at `0x200`, points the PC there and ticks once
+    /// per opcode, stopping early if a jump takes the PC outside the written
+    /// opcodes. Registers, timers and the rest of RAM are left as they are,
+    /// so they can be set up beforehand. A program that doesn't fit between
+    /// `0x200` and the end of RAM is rejected with `Chip8Error::RomTooLarge`
+    /// before anything runs.
+    pub fn run_program(&mut self, ops: &[u16]) -> Result<(), Chip8Error> {
+        let bytes: Vec<u8> = ops.iter().flat_map(|op| op.to_be_bytes()).collect();
+        let end = START_ADDR as usize + bytes.len();
+
+        self.load_into_ram(&bytes, START_ADDR)?;
+        self.pc = START_ADDR;
 
-        if self.v_reg[x] != nn {
-            self.pc += 2;
+        for _ in ops {
+            if self.pc < START_ADDR || self.pc as usize >= end {
+                break;
+            }
+
+            self.tick()?;
         }
+
+        Ok(())
     }
 
-    fn skip_if_vx_equals_vy(&mut self, second_digit: u16, third_digit: u16) {
-        let x = second_digit as usize;
-        let y = third_digit as usize;
+    /// Like `tick`, but replaces the keypad state with the result of `poll`
+    /// right before the instruction runs, so `SKP`/`SKNP`/`LD VX, K` see input
+    /// sampled mid-frame. `poll` is called once per instruction and should be
+    /// cheap.
+    pub fn tick_with_input<F: FnMut() -> [bool; NUM_KEYS]>(
+        &mut self,
+        mut poll: F,
+    ) -> Result<(), Chip8Error> {
+        self.keys = poll();
+        self.tick()
+    }
 
-        if self.v_reg[x] == self.v_reg[y] {
-            self.pc += 2;
+    pub fn tick_timers(&mut self) {
+        if self.history_capacity > 0 {
+            if self.history.len() == self.history_capacity {
+                self.history.pop_front();
+            }
+
+            self.history.push_back(self.state());
+        }
+
+        self.timer_ticks += 1;
+
+        // This is the vblank any stalled DRW is waiting on
+        self.vblank_released = self.awaiting_vblank;
+        self.awaiting_vblank = false;
+        self.drawn_this_frame = false;
+
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        // The buzzer sounds for as long as this is non-zero; see is_sound_active
+        if self.sound_timer > 0 {
+            self.set_sound_timer(self.sound_timer - 1);
         }
     }
 
-    fn assign_nn_to_vx(&mut self, second_digit: u16, nn: u16) {
-        let x = second_digit as usize;
-        let nn = nn as u8;
+    // Every change to the sound timer goes through here so the sound
+    // callback sees each start and stop exactly once
+    fn set_sound_timer(&mut self, value: u8) {
+        let was_sounding = self.is_sound_active();
 
-        self.v_reg[x] = nn;
+        self.sound_timer = value;
+
+        if self.is_sound_active() != was_sounding {
+            self.notify_sound(!was_sounding);
+        }
     }
 
-    fn add_nn_to_vx(&mut self, second_digit: u16, nn: u16) {
-        let x = second_digit as usize;
-        let nn = nn as u8;
+    fn notify_sound(&mut self, active: bool) {
+        if let Some(callback) = self.sound_callback.as_mut() {
+            callback(active);
+        }
+    }
 
-        self.v_reg[x] = self.v_reg[x].wrapping_add(nn);
+    /// Runs as many `tick_timers` calls as `clock` says are due at 60Hz and
+    /// returns how many ran. The first call only notes the time. If the host
+    /// stalls for more than a second the backlog is dropped rather than
+    /// replayed all at once.
+    pub fn advance_time(&mut self, clock: &impl Clock) -> u64 {
+        let now = clock.now_micros();
+        let (base_micros, base_ticks) = *self.clock_base.get_or_insert((now, self.timer_ticks));
+        let due = base_ticks + now.saturating_sub(base_micros) * TIMER_HZ / 1_000_000;
+        let behind = due.saturating_sub(self.timer_ticks);
+
+        if behind > MAX_CATCH_UP {
+            self.clock_base = Some((now, self.timer_ticks));
+            return 0;
+        }
+
+        for _ in 0..behind {
+            self.tick_timers();
+        }
+
+        behind
     }
 
-    fn assign_vx_to_vy(&mut self, second_digit: u16, third_digit: u16) {
-        let x = second_digit as usize;
-        let y = third_digit as usize;
+    /// Number of instructions executed since power-on or the last reset.
+    pub fn cycle_count(&self) -> u64 {
+        self.cycle_count
+    }
 
-        self.v_reg[x] = self.v_reg[y];
+    /// Approximate COSMAC VIP machine cycles spent on the instructions run
+    /// so far. A frontend can run instructions until this has advanced by
+    /// `VIP_CYCLES_PER_FRAME` to reproduce the original speed, including the
+    /// slowdown on frames heavy with sprite drawing.
+    pub fn vip_cycles(&self) -> u64 {
+        self.vip_cycles
     }
 
-    fn vx_or_vy(&mut self, second_digit: u16, third_digit: u16) {
-        let x = second_digit as usize;
-        let y = third_digit as usize;
+    /// Stable hash of the CPU, RAM and screen, for cheaply comparing runs.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = fingerprint::Fnv1a::new();
 
-        self.v_reg[x] |= self.v_reg[y];
+        hasher.write(&self.pc.to_be_bytes());
+        hasher.write(&self.i_reg.to_be_bytes());
+        hasher.write(&self.v_reg);
+        hasher.write(&self.stack_ptr.to_be_bytes());
+
+        for addr in self.stack {
+            hasher.write(&addr.to_be_bytes());
+        }
+
+        hasher.write(&[self.delay_timer, self.sound_timer]);
+        hasher.write(&self.ram);
+
+        for &pixel in &self.screen {
+            hasher.write(&[pixel as u8]);
+        }
+
+        hasher.finish()
     }
 
-    fn vx_and_vy(&mut self, second_digit: u16, third_digit: u16) {
-        let x = second_digit as usize;
-        let y = third_digit as usize;
+    /// Snapshot of everything the running program can observe.
+    pub fn state(&self) -> EmulatorState {
+        EmulatorState {
+            pc: self.pc,
+            i_reg: self.i_reg,
+            v_reg: self.v_reg,
+            stack_ptr: self.stack_ptr,
+            stack: self.stack,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            ram: self.ram,
+            screen: self.screen.to_vec(),
+        }
+    }
 
-        self.v_reg[x] &= self.v_reg[y];
+    /// Captures the machine, including held keys, for a frontend to save.
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            pc: self.pc,
+            ram: self.ram,
+            screen: self.screen.to_vec(),
+            v_reg: self.v_reg,
+            i_reg: self.i_reg,
+            stack_ptr: self.stack_ptr,
+            stack: self.stack,
+            keys: self.keys,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+        }
     }
 
-    fn vx_xor_vy(&mut self, second_digit: u16, third_digit: u16) {
-        let x = second_digit as usize;
-        let y = third_digit as usize;
+    /// Resumes from a `SaveState`. Like `restore`, host-side configuration
+    /// is left alone. A saved screen of the wrong size is copied as far as
+    /// it goes, so a corrupt file can't panic the emulator.
+    pub fn load_state(&mut self, state: SaveState) {
+        self.pc = state.pc;
+        self.ram = state.ram;
+        self.v_reg = state.v_reg;
+        self.i_reg = state.i_reg;
+        self.stack_ptr = state.stack_ptr.min(STACK_SIZE as u16);
+        self.stack = state.stack;
+        self.keys = state.keys;
+        self.delay_timer = state.delay_timer;
+        self.set_sound_timer(state.sound_timer);
+        self.waiting_for_key = None;
+        self.pressed_while_waiting = None;
+        self.halted = false;
+
+        self.set_hires(state.screen.len() == HIRES_WIDTH * HIRES_HEIGHT);
+
+        for (pixel, &saved) in self.screen.iter_mut().zip(&state.screen) {
+            *pixel = saved;
+        }
+    }
 
-        self.v_reg[x] ^= self.v_reg[y];
+    /// Snapshot of just the CPU registers, timers and stack: much cheaper
+    /// than `state` when RAM and the screen don't matter.
+    pub fn register_snapshot(&self) -> RegisterFile {
+        RegisterFile {
+            pc: self.pc,
+            i_reg: self.i_reg,
+            v_reg: self.v_reg,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            stack: self.stack[..self.stack_ptr as usize].to_vec(),
+        }
     }
 
-    fn add_vy_to_vx(&mut self, second_digit: u16, third_digit: u16) {
-        let x = second_digit as usize;
-        let y = third_digit as usize;
-        let vy = self.v_reg[y];
+    /// Puts the machine back into a previously captured state. Host-side
+    /// configuration such as quirks and hooks is left alone. As with
+    /// `load_state`, a bad stack pointer or screen size can't panic.
+    pub fn restore(&mut self, state: &EmulatorState) {
+        self.pc = state.pc;
+        self.i_reg = state.i_reg;
+        self.v_reg = state.v_reg;
+        self.stack_ptr = state.stack_ptr.min(STACK_SIZE as u16);
+        self.stack = state.stack;
+        self.delay_timer = state.delay_timer;
+        self.set_sound_timer(state.sound_timer);
+        self.ram = state.ram;
+        self.set_hires(state.screen.len() == HIRES_WIDTH * HIRES_HEIGHT);
+
+        for (pixel, &saved) in self.screen.iter_mut().zip(&state.screen) {
+            *pixel = saved;
+        }
 
-        let (vx, carry) = self.v_reg[x].overflowing_add(vy);
-        let vf = if carry { 1 } else { 0 };
+        self.waiting_for_key = None;
+        self.pressed_while_waiting = None;
+        self.halted = false;
+    }
 
-        self.v_reg[x] = vx;
-        self.v_reg[0xF] = vf;
+    /// Starts keeping a snapshot of the machine at every `tick_timers`, up to
+    /// `capacity` frames back, so `rewind` can step backwards in time.
+    pub fn enable_history(&mut self, capacity: usize) {
+        self.set_history_capacity(capacity);
     }
 
-    fn sub_vy_from_vx(&mut self, second_digit: u16, third_digit: u16) {
-        let x = second_digit as usize;
-        let y = third_digit as usize;
-        let vy = self.v_reg[y];
+    /// Changes how many frames of history are kept, dropping the oldest ones
+    /// if there are now too many. A capacity of 0 turns history off.
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
 
-        let (vx, carry) = self.v_reg[x].overflowing_sub(vy);
-        let vf = if carry { 0 } else { 1 };
+        self.history_capacity = capacity;
+    }
 
-        self.v_reg[x] = vx;
-        self.v_reg[0xF] = vf;
+    /// Number of frames `rewind` can currently go back.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
     }
 
-    fn lshift_vx(&mut self, second_digit: u16) {
-        let x = second_digit as usize;
-        let msb = (self.v_reg[x] >> 7) & 1;
+    /// Returns the machine to the start of the most recent recorded frame,
+    /// or returns `false` if there is no history left.
+    pub fn rewind(&mut self) -> bool {
+        match self.history.pop_back() {
+            Some(state) => {
+                self.restore(&state);
+                true
+            }
+            None => false,
+        }
+    }
 
-        self.v_reg[x] <<= 1;
-        self.v_reg[0xF] = msb;
+    /// Lists how this emulator differs from `other`, e.g. a state captured
+    /// from another run or another interpreter. Each entry prints as a
+    /// readable line such as `V3: 0x05 != 0x07`.
+    pub fn diff_state(&self, other: &EmulatorState) -> Vec<StateDiff> {
+        self.state().diff(other)
     }
 
-    fn rshift_vx(&mut self, second_digit: u16) {
-        let x = second_digit as usize;
-        let lsb = self.v_reg[x] & 1;
+    /// Stable hash of just the screen contents, e.g. to check that a replay
+    /// still ends on the same picture.
+    pub fn screen_hash(&self) -> u64 {
+        let mut hasher = fingerprint::Fnv1a::new();
 
-        self.v_reg[x] >>= 1;
-        self.v_reg[0xF] = lsb;
+        for &pixel in &self.screen {
+            hasher.write(&[pixel as u8]);
+        }
+
+        hasher.finish()
     }
 
-    fn sub_vx_from_vy(&mut self, second_digit: u16, third_digit: u16) {
-        let x = second_digit as usize;
-        let y = third_digit as usize;
-        let vx = self.v_reg[x];
+    /// Emulated time since power-on or the last reset, counted in 60Hz timer
+    /// ticks rather than host time.
+    pub fn emulated_time(&self) -> Duration {
+        Duration::from_nanos(self.timer_ticks * 1_000_000_000 / TIMER_HZ)
+    }
 
-        let (vy, carry) = self.v_reg[y].overflowing_sub(vx);
-        let vf = if carry { 0 } else { 1 };
+    /// Whether the buzzer should be sounding, i.e. the sound timer is
+    /// running. Frontends poll this once per frame to drive their audio.
+    pub fn is_sound_active(&self) -> bool {
+        self.sound_timer > 0
+    }
 
-        self.v_reg[y] = vy;
-        self.v_reg[0xF] = vf;
+    pub fn registers(&self) -> &[u8; REGISTER_COUNT] {
+        &self.v_reg
     }
 
-    fn skip_if_vx_not_equals_vy(&mut self, second_digit: u16, third_digit: u16) {
-        let x = second_digit as usize;
-        let y = third_digit as usize;
+    pub fn set_register(&mut self, x: usize, val: u8) {
+        self.v_reg[x] = val;
+    }
 
-        if self.v_reg[x] != self.v_reg[y] {
-            self.pc += 2;
-        }
+    pub fn index(&self) -> u16 {
+        self.i_reg
     }
 
-    fn assign_nnn_to_ireg(&mut self, nnn: u16) {
-        self.i_reg = nnn
+    pub fn program_counter(&self) -> u16 {
+        self.pc
     }
 
-    fn jump_to_offset(&mut self, nnn: u16) {
-        self.pc = (self.v_reg[0] as u16) + nnn;
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
     }
 
-    fn assign_rand_and_nn_to_vx(&mut self, second_digit: u16, nn: u16) {
-        let x = second_digit as usize;
-        let nn = nn as u8;
-        let rng: u8 = random();
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
 
-        self.v_reg[x] = rng & nn;
+    /// Blanks the screen as `CLS` would, leaving the CPU untouched.
+    pub fn clear_display(&mut self) {
+        self.clear_screen();
+    }
+
+    /// Logical resolution of the display in CHIP-8 pixels, as `(width,
+    /// height)`: 64x32, or 128x64 after a SUPER-CHIP ROM runs `00FF`.
+    /// `get_display` is laid out row-major at this size and can change size
+    /// between frames; scaling it to the host window is left entirely to the
+    /// frontend.
+    pub fn display_size(&self) -> (usize, usize) {
+        if self.hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        }
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    pub fn get_display(&self) -> &[bool] {
+        &self.screen
     }
 
-    fn draw_sprite(&mut self, vx: u16, vy: u16, num_rows: u16) {
-        let x_coord = self.v_reg[vx as usize] as u16;
-        let y_coord = self.v_reg[vy as usize] as u16;
+    /// Whether anything has drawn to or cleared the screen since the last
+    /// call, resetting the flag. A frontend can skip redrawing when it's
+    /// false. Always true the first time after power-on or `reset`.
+    pub fn take_display_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.display_dirty)
+    }
 
-        let mut flipped = false;
+    /// The screen as a `Display`, addressed by `(x, y)`.
+    pub fn display(&self) -> Display<'_> {
+        let (width, height) = self.display_size();
+        Display::new(&self.screen, width, height)
+    }
 
-        for y_line in 0..num_rows {
-            let addr = self.i_reg + y_line as u16;
-            let pixels = self.ram[addr as usize];
+    /// The screen as RGBA bytes, row by row at the current resolution, with
+    /// `fg` for lit pixels and `bg` for dark ones. Any backend that takes
+    /// 32-bit RGBA pixels can draw it directly.
+    pub fn framebuffer_rgba(&self, fg: [u8; 4], bg: [u8; 4]) -> Vec<u8> {
+        self.screen
+            .iter()
+            .flat_map(|&lit| if lit { fg } else { bg })
+            .collect()
+    }
 
-            for x_line in 0..8 {
-                if (pixels & (0b1000_0000 >> x_line)) != 0 {
-                    let x = (x_coord + x_line) as usize % SCREEN_WIDTH;
-                    let y = (y_coord + y_line) as usize % SCREEN_HEIGHT;
+    /// The screen as text, one line per row with `█` for a lit pixel and a
+    /// space for a dark one. Readable in a terminal and in `assert_eq!`
+    /// diffs.
+    pub fn screen_to_ascii(&self) -> String {
+        let (width, height) = self.display_size();
+        let mut text = String::with_capacity(self.screen.len() * '█'.len_utf8() + height);
 
-                    let idx = x + SCREEN_WIDTH * y;
-                    flipped |= self.screen[idx];
-                    self.screen[idx] ^= true;
-                }
-            }
+        for row in self.screen.chunks(width) {
+            text.extend(row.iter().map(|&lit| if lit { '█' } else { ' ' }));
+            text.push('\n');
         }
 
-        self.v_reg[0xF] = flipped.into()
+        text
     }
 
-    fn skip_if_key_pressed(&mut self, x: u16) {
-        let vx = self.v_reg[x as usize];
-        let key = self.keys[vx as usize];
+    /// Whether the program has exited with `00FD`. Only `reset`, `load` or
+    /// restoring a state brings it back.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
 
-        if key {
-            self.pc += 2;
-        }
+    /// Whether the CPU is blocked on `LD VX, K` until a key is pressed.
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waiting_for_key.is_some()
     }
 
-    fn skip_if_key_not_pressed(&mut self, x: u16) {
-        let vx = self.v_reg[x as usize];
-        let key = self.keys[vx as usize];
+    /// While blocked on `LD VX, K`, returns `Some(x)` for the register `VX`
+    /// that will receive the key, so a frontend can show a "press any key"
+    /// prompt. Any key satisfies the wait.
+    pub fn expected_key_hint(&self) -> Option<u8> {
+        self.waiting_for_key
+    }
 
-        if !key {
-            self.pc += 2;
+    /// Which keys are currently held, indexed by CHIP-8 key.
+    pub fn keys(&self) -> &[bool; NUM_KEYS] {
+        &self.keys
+    }
+
+    pub fn keypress(&mut self, idx: usize, pressed: bool) {
+        self.keys[idx] = pressed;
+    }
+
+    /// Resets the machine and loads a ROM at `0x200`, so nothing from a
+    /// previously loaded program survives. A ROM that doesn't fit in RAM is
+    /// rejected with `Chip8Error::RomTooLarge` and the machine is left as is.
+    pub fn load(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+        let max = RAM_SIZE - START_ADDR as usize;
+
+        if data.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: data.len(),
+                max,
+            });
         }
+
+        self.reset();
+        self.load_into_ram(data, START_ADDR)
     }
 
-    fn assign_dt_to_vx(&mut self, x: u16) {
-        let x = x as usize;
-        self.v_reg[x] = self.delay_timer;
+    /// Like `load`, but a ROM too big for RAM is cut off at the end of RAM
+    /// instead of rejected. Returns how many trailing bytes were dropped, so
+    /// damaged or concatenated files can still be inspected.
+    pub fn load_truncating(&mut self, data: &[u8]) -> usize {
+        let max = RAM_SIZE - START_ADDR as usize;
+        let kept = data.len().min(max);
+
+        self.reset();
+        self.ram[START_ADDR as usize..][..kept].copy_from_slice(&data[..kept]);
+        data.len() - kept
     }
 
-    fn wait_for_key_press(&mut self, x: u16) {
-        let x = x as usize;
-        let mut pressed = false;
+    /// Copies `data` into RAM starting at `addr` without touching registers,
+    /// timers, the screen or any other RAM. Like `load`, nothing is written
+    /// if `addr` is outside RAM (`Chip8Error::InvalidAddress`) or the data
+    /// would run past the end of it (`Chip8Error::RomTooLarge`).
+    pub fn load_into_ram(&mut self, data: &[u8], addr: u16) -> Result<(), Chip8Error> {
+        let start = addr as usize;
 
-        for i in 0..self.keys.len() {
-            if self.keys[i] {
-                self.v_reg[x] = i as u8;
-                pressed = true;
-                break;
-            }
+        if start >= RAM_SIZE {
+            return Err(Chip8Error::InvalidAddress { addr });
         }
 
-        if !pressed {
-            self.pc -= 2;
+        let max = RAM_SIZE - start;
+
+        if data.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: data.len(),
+                max,
+            });
         }
-    }
 
-    fn assign_vx_to_dt(&mut self, x: u16) {
-        let vx = self.v_reg[x as usize];
-        self.delay_timer = vx;
+        self.ram[start..start + data.len()].copy_from_slice(data);
+        Ok(())
     }
 
-    fn assign_vx_to_st(&mut self, x: u16) {
-        let vx = self.v_reg[x as usize];
-        self.sound_timer = vx;
+    /// Borrows `len` bytes of RAM starting at `start`, or `None` if the range
+    /// runs past the end of RAM.
+    pub fn mem_slice(&self, start: u16, len: usize) -> Option<&[u8]> {
+        let start = start as usize;
+        self.ram.get(start..start.checked_add(len)?)
     }
 
-    fn add_vx_to_ireg(&mut self, x: u16) {
-        let vx = self.v_reg[x as usize] as u16;
-        self.i_reg = self.i_reg.wrapping_add(vx);
+    /// Reads a single RAM byte, or `None` if `addr` is past the end of RAM.
+    pub fn read_byte(&self, addr: u16) -> Option<u8> {
+        self.ram.get(addr as usize).copied()
     }
 
-    fn assign_font_addr_to_ireg(&mut self, x: u16) {
-        let x = x as usize;
-        let c = self.v_reg[x] as u16;
-        self.i_reg = c * 5;
+    /// Writes a single RAM byte from the host side, e.g. from a debugger.
+    pub fn write_byte(&mut self, addr: u16, val: u8) -> Result<(), Chip8Error> {
+        self.apply_patch(&[(addr, val)])
     }
 
-    fn assign_vx_bcd_to_ireg(&mut self, x: u16) {
-        let vx = self.v_reg[x as usize] as f32;
+    /// Overwrites individual RAM bytes, e.g. to apply a fix or cheat to a
+    /// loaded ROM. Every address is checked before anything is written, so
+    /// an invalid patch leaves RAM untouched.
+    pub fn apply_patch(&mut self, patch: &[(u16, u8)]) -> Result<(), Chip8Error> {
+        if let Some(&(addr, _)) = patch.iter().find(|(addr, _)| *addr as usize >= RAM_SIZE) {
+            return Err(Chip8Error::InvalidAddress { addr });
+        }
 
-        let hundreds = (vx / 100.0).floor() as u8;
-        let tens = ((vx / 10.0) % 10.0).floor() as u8;
-        let ones = (vx % 10.0) as u8;
+        for &(addr, val) in patch {
+            self.ram[addr as usize] = val;
+        }
 
-        self.ram[self.i_reg as usize] = hundreds;
-        self.ram[(self.i_reg + 1) as usize] = tens;
-        self.ram[(self.i_reg + 2) as usize] = ones;
+        Ok(())
     }
 
-    fn store_regs_into_ram(&mut self, x: u16) {
-        let x = x as usize;
-        let i = self.i_reg as usize;
+    /// Disassembles the instruction currently in RAM at `addr`, or `None` if
+    /// it would read past the end of RAM.
+    pub fn decode_at(&self, addr: u16) -> Option<String> {
+        self.opcode_at(addr).map(disasm::disassemble)
+    }
 
-        for idx in 0..=x {
-            self.ram[i + idx] = self.v_reg[idx];
+    /// Decodes the instruction at the PC into its mnemonic and operands, or
+    /// `None` if the PC is at the end of RAM.
+    pub fn current_instruction(&self) -> Option<disasm::DecodedInstruction> {
+        self.opcode_at(self.pc).map(disasm::decode)
+    }
+
+    fn opcode_at(&self, addr: u16) -> Option<u16> {
+        let addr = addr as usize;
+
+        if addr + 1 >= RAM_SIZE {
+            return None;
         }
+
+        Some(((self.ram[addr] as u16) << 8) | self.ram[addr + 1] as u16)
     }
 
-    fn load_ram_into_regs(&mut self, x: u16) {
-        let x = x as usize;
-        let i = self.i_reg as usize;
+    /// Whether the CPU is stuck in the common busy-wait on the delay timer:
+    ///
+    /// ```text
+    /// loop: LD VX, DT
+    ///       SE VX, 0
+    ///       JP loop
+    /// ```
+    ///
+    /// While the delay timer is non-zero nothing can change until the next
+    /// `tick_timers`, so a frontend can skip the rest of the frame's ticks
+    /// without any observable difference.
+    pub fn detect_delay_spin(&self) -> bool {
+        self.delay_timer > 0 && (0..3).any(|i| self.is_delay_spin_at(self.pc.wrapping_sub(i * 2)))
+    }
 
-        for idx in 0..=x {
-            self.v_reg[idx] = self.ram[i + idx];
-        }
-    }
-
-    fn execute(&mut self, op: u16) {
-        let first_digit = (op & 0xF000) >> 12;
-        let second_digit = (op & 0x0F00) >> 8;
-        let third_digit = (op & 0x00F0) >> 4;
-        let fourth_digit = op & 0x000F;
-
-        let nnn = op & 0xFFF;
-        let nn = op & 0xFF;
-
-        match (first_digit, second_digit, third_digit, fourth_digit) {
-            (0, 0, 0, 0) => (),                                                       // NOP
-            (0, 0, 0xE, 0) => self.clear_screen(),                                    // CLS
-            (0, 0, 0xE, 0xE) => self.end_subroutine(),                                // RET
-            (1, _, _, _) => self.jump(nnn),                                           // JMP
-            (2, _, _, _) => self.call_subroutine(nnn),                                // CALL
-            (3, _, _, _) => self.skip_if_vx_equals_nn(second_digit, nn),              // SE VX, NN
-            (4, _, _, _) => self.skip_if_vx_not_equals_nn(second_digit, nn),          // SNE VX, NN
-            (5, _, _, _) => self.skip_if_vx_equals_vy(second_digit, third_digit),     // SE VX, VY
-            (6, _, _, _) => self.assign_nn_to_vx(second_digit, nn),                   // VX == NN
-            (7, _, _, _) => self.add_nn_to_vx(second_digit, nn),                      // VX += NN
-            (8, _, _, 0) => self.assign_vx_to_vy(second_digit, third_digit),          // VX = VY
-            (8, _, _, 1) => self.vx_or_vy(second_digit, third_digit),                 // VX |= VY
-            (8, _, _, 2) => self.vx_and_vy(second_digit, third_digit),                // VX &= VY
-            (8, _, _, 3) => self.vx_xor_vy(second_digit, third_digit),                // VX ^= VY
-            (8, _, _, 4) => self.add_vy_to_vx(second_digit, third_digit),             // VX += VY
-            (8, _, _, 5) => self.sub_vy_from_vx(second_digit, third_digit),           // VX -= VY
-            (8, _, _, 6) => self.rshift_vx(second_digit),                             // VX >>= 1
-            (8, _, _, 7) => self.sub_vx_from_vy(second_digit, third_digit), // VX = VY - VX
-            (8, _, _, 0xE) => self.lshift_vx(second_digit),                 // VX <<= 1
-            (9, _, _, 0) => self.skip_if_vx_not_equals_vy(second_digit, third_digit), // SNE VX, VY
-            (0xA, _, _, _) => self.assign_nnn_to_ireg(nnn),                 // I = NNN
-            (0xB, _, _, _) => self.jump_to_offset(nnn),                     // JMP V0 + NNN
-            (0xC, _, _, _) => self.assign_rand_and_nn_to_vx(second_digit, nn), // VX = RAND & NN
-            (0xD, _, _, _) => self.draw_sprite(second_digit, third_digit, fourth_digit), // DRW
-            (0xE, _, 9, 0xE) => self.skip_if_key_pressed(second_digit),     // SKP
-            (0xE, _, 0xA, 1) => self.skip_if_key_not_pressed(second_digit), //SKNP
-            (0xF, _, 0, 7) => self.assign_dt_to_vx(second_digit),           // VX = DT
-            (0xF, _, 0, 0xA) => self.wait_for_key_press(second_digit),      // LD VX, K
-            (0xF, _, 1, 5) => self.assign_vx_to_dt(second_digit),           // LD DT, VX
-            (0xF, _, 1, 8) => self.assign_vx_to_st(second_digit),           // LD ST, VX
-            (0xF, _, 1, 0xE) => self.add_vx_to_ireg(second_digit),          // I += VX
-            (0xF, _, 2, 9) => self.assign_font_addr_to_ireg(second_digit),  // LD F, VX
-            (0xF, _, 3, 3) => self.assign_vx_bcd_to_ireg(second_digit),     // LD B, VX
-            (0xF, _, 5, 5) => self.store_regs_into_ram(second_digit),       // LD [I], VX
-            (0xF, _, 6, 5) => self.load_ram_into_regs(second_digit),        // LD VX, [I]
-            _ => unimplemented!("Unimplemented opcode: {}", op),
+    fn is_delay_spin_at(&self, addr: u16) -> bool {
+        let ops = (
+            self.opcode_at(addr),
+            self.opcode_at(addr.wrapping_add(2)),
+            self.opcode_at(addr.wrapping_add(4)),
+        );
+
+        match ops {
+            (Some(read), Some(skip), Some(jump)) => matches!(
+                (
+                    Instruction::decode(read),
+                    Instruction::decode(skip),
+                    Instruction::decode(jump),
+                ),
+                (
+                    Instruction::LoadFromDelay { x },
+                    Instruction::SkipEqByte { x: skip_x, nn: 0 },
+                    Instruction::Jump { nnn },
+                ) if skip_x == x && nnn == addr
+            ),
+            _ => false,
         }
     }
+
+    /// Marks `addr` so that `at_breakpoint` reports when the PC reaches it.
+    /// The emulator itself never stops; frontends check after each tick,
+    /// e.g. with `run_until(Emulator::at_breakpoint, n)`.
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Whether the next instruction to run sits on a breakpoint.
+    pub fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.pc)
+    }
+
+    /// Rejects any `LD [I], VX` or `LD B, VX` store that touches
+    /// `start..end` with `Chip8Error::ProtectedWrite`, leaving RAM untouched.
+    pub fn protect_range(&mut self, start: u16, end: u16) {
+        self.protected = Some((start, end));
+    }
+
+    pub fn clear_protection(&mut self) {
+        self.protected = None;
+    }
+
+    /// In strict mode, any instruction reading or writing RAM through `I`
+    /// (`DRW`, `LD B, VX`, `LD [I], VX` and `LD VX, [I]`) fails with
+    /// `Chip8Error::MemoryOutOfBounds` if the access runs past the end of RAM.
+    /// Otherwise the access wraps around to the start of RAM like the 12-bit
+    /// address bus would.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn set_unknown_opcode_policy(&mut self, policy: UnknownOpcodePolicy) {
+        self.unknown_opcode_policy = policy;
+    }
+
+    /// Every unknown opcode skipped or reported so far with how many times it
+    /// was hit, in opcode order. Handy for working out why a ROM misbehaves.
+    pub fn unknown_opcodes(&self) -> Vec<(u16, u64)> {
+        self.unknown_opcodes
+            .iter()
+            .map(|(&op, &count)| (op, count))
+            .collect()
+    }
+
+    // Only fails in strict mode; callers wrap each address with `% RAM_SIZE`
+    // so the lenient path can't index past the end of RAM either
+    fn check_bounds(&self, start: usize, len: usize) -> Result<(), Chip8Error> {
+        if self.strict && start + len > RAM_SIZE {
+            return Err(Chip8Error::MemoryOutOfBounds {
+                addr: start.max(RAM_SIZE),
+                pc: self.pc - 2,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn check_write(&self, start: usize, len: usize) -> Result<(), Chip8Error> {
+        self.check_bounds(start, len)?;
+
+        if let Some((lo, hi)) = self.protected {
+            for addr in start..start + len {
+                let addr = addr % RAM_SIZE;
+
+                if addr >= lo as usize && addr < hi as usize {
+                    return Err(Chip8Error::ProtectedWrite {
+                        addr: addr as u16,
+                        pc: self.pc - 2,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fetch(&mut self) -> Result<u16, Chip8Error> {
+        // Both bytes of the opcode have to be in RAM
+        if self.pc as usize + 1 >= RAM_SIZE {
+            return Err(Chip8Error::PcOutOfBounds { pc: self.pc });
+        }
+
+        let higher_byte = self.ram[self.pc as usize] as u16;
+        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
+        let op = (higher_byte << 8) | lower_byte;
+
+        self.pc += 2;
+        Ok(op)
+    }
+
+    fn push(&mut self, val: u16) -> Result<(), Chip8Error> {
+        if self.stack_ptr as usize >= STACK_SIZE {
+            return Err(Chip8Error::StackOverflow { pc: self.pc - 2 });
+        }
+
+        self.stack[self.stack_ptr as usize] = val;
+        self.stack_ptr += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<u16, Chip8Error> {
+        if self.stack_ptr == 0 {
+            return Err(Chip8Error::StackUnderflow { pc: self.pc - 2 });
+        }
+
+        self.stack_ptr -= 1;
+        Ok(self.stack[self.stack_ptr as usize])
+    }
+
+    // Instructions
+
+    fn clear_screen(&mut self) {
+        self.screen.fill(false);
+        self.display_dirty = true;
+    }
+
+    // Switching resolution clears the screen, as it does in most SUPER-CHIP
+    // interpreters
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.display_dirty = true;
+
+        let (width, height) = self.display_size();
+        self.screen = vec![false; width * height];
+    }
+
+    // SCHIP scrolls move the whole screen, clearing whatever scrolls in
+    fn scroll_down(&mut self, rows: u16) {
+        let len = self.screen.len();
+        let (width, height) = self.display_size();
+        let shift = (rows as usize).min(height) * width;
+
+        self.screen.copy_within(..len - shift, shift);
+        self.screen[..shift].fill(false);
+        self.display_dirty = true;
+    }
+
+    fn scroll_right(&mut self) {
+        let (width, _) = self.display_size();
+
+        for row in self.screen.chunks_mut(width) {
+            row.copy_within(..width - 4, 4);
+            row[..4].fill(false);
+        }
+
+        self.display_dirty = true;
+    }
+
+    fn scroll_left(&mut self) {
+        let (width, _) = self.display_size();
+
+        for row in self.screen.chunks_mut(width) {
+            row.copy_within(4.., 0);
+            row[width - 4..].fill(false);
+        }
+
+        self.display_dirty = true;
+    }
+
+    fn end_subroutine(&mut self) -> Result<(), Chip8Error> {
+        let ret_addr = self.pop()?;
+        self.pc = ret_addr;
+        Ok(())
+    }
+
+    fn jump(&mut self, nnn: u16) {
+        self.pc = nnn;
+    }
+
+    fn call_subroutine(&mut self, nnn: u16) -> Result<(), Chip8Error> {
+        self.push(self.pc)?;
+        self.pc = nnn;
+        Ok(())
+    }
+
+    fn skip_if_vx_equals_nn(&mut self, second_digit: u16, nn: u16) {
+        let x = second_digit as usize;
+        let nn = nn as u8;
+
+        if self.v_reg[x] == nn {
+            self.pc += 2;
+        }
+    }
+
+    fn skip_if_vx_not_equals_nn(&mut self, second_digit: u16, nn: u16) {
+        let x = second_digit as usize;
+        let nn = nn as u8;
+
+        if self.v_reg[x] != nn {
+            self.pc += 2;
+        }
+    }
+
+    fn skip_if_vx_equals_vy(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+        let y = third_digit as usize;
+
+        if self.v_reg[x] == self.v_reg[y] {
+            self.pc += 2;
+        }
+    }
+
+    fn assign_nn_to_vx(&mut self, second_digit: u16, nn: u16) {
+        let x = second_digit as usize;
+        let nn = nn as u8;
+
+        self.v_reg[x] = nn;
+    }
+
+    fn add_nn_to_vx(&mut self, second_digit: u16, nn: u16) {
+        let x = second_digit as usize;
+        let nn = nn as u8;
+
+        self.v_reg[x] = self.v_reg[x].wrapping_add(nn);
+    }
+
+    fn assign_vx_to_vy(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+        let y = third_digit as usize;
+
+        self.v_reg[x] = self.v_reg[y];
+    }
+
+    fn vx_or_vy(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+        let y = third_digit as usize;
+
+        self.v_reg[x] |= self.v_reg[y];
+    }
+
+    fn vx_and_vy(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+        let y = third_digit as usize;
+
+        self.v_reg[x] &= self.v_reg[y];
+    }
+
+    fn vx_xor_vy(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+        let y = third_digit as usize;
+
+        self.v_reg[x] ^= self.v_reg[y];
+    }
+
+    fn add_vy_to_vx(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+        let y = third_digit as usize;
+        let vy = self.v_reg[y];
+
+        let (vx, carry) = self.v_reg[x].overflowing_add(vy);
+        let vf = if carry { 1 } else { 0 };
+
+        self.v_reg[x] = vx;
+        self.v_reg[0xF] = vf;
+    }
+
+    fn sub_vy_from_vx(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+        let y = third_digit as usize;
+        let vy = self.v_reg[y];
+
+        let (vx, carry) = self.v_reg[x].overflowing_sub(vy);
+        let vf = if carry { 0 } else { 1 };
+
+        self.v_reg[x] = vx;
+        self.v_reg[0xF] = vf;
+    }
+
+    fn lshift_vx(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+
+        if self.quirks.shift_uses_vy {
+            self.v_reg[x] = self.v_reg[third_digit as usize];
+        }
+
+        let msb = (self.v_reg[x] >> 7) & 1;
+
+        self.v_reg[x] <<= 1;
+        self.v_reg[0xF] = msb;
+    }
+
+    fn rshift_vx(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+
+        if self.quirks.shift_uses_vy {
+            self.v_reg[x] = self.v_reg[third_digit as usize];
+        }
+
+        let lsb = self.v_reg[x] & 1;
+
+        self.v_reg[x] >>= 1;
+        self.v_reg[0xF] = lsb;
+    }
+
+    fn sub_vx_from_vy(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+        let y = third_digit as usize;
+        let vx = self.v_reg[x];
+
+        let (vy, carry) = self.v_reg[y].overflowing_sub(vx);
+        let vf = if carry { 0 } else { 1 };
+
+        self.v_reg[y] = vy;
+        self.v_reg[0xF] = vf;
+    }
+
+    fn skip_if_vx_not_equals_vy(&mut self, second_digit: u16, third_digit: u16) {
+        let x = second_digit as usize;
+        let y = third_digit as usize;
+
+        if self.v_reg[x] != self.v_reg[y] {
+            self.pc += 2;
+        }
+    }
+
+    fn assign_nnn_to_ireg(&mut self, nnn: u16) {
+        self.i_reg = nnn
+    }
+
+    fn jump_to_offset(&mut self, second_digit: u16, nnn: u16) {
+        let x = if self.quirks.jump_uses_vx {
+            second_digit as usize
+        } else {
+            0
+        };
+
+        // V0 + NNN can reach 0x10FE, so wrap it back into the 12-bit address space
+        self.pc = ((self.v_reg[x] as u16) + nnn) & 0xFFF;
+    }
+
+    fn assign_rand_and_nn_to_vx(&mut self, second_digit: u16, nn: u16) {
+        let x = second_digit as usize;
+        let nn = nn as u8;
+        let rng = self.rng.next_u8();
+
+        self.v_reg[x] = rng & nn;
+    }
+
+    // Returns true if the DRW has to stall until the next vblank, in which
+    // case the PC is rewound so it runs again after `tick_timers`.
+    fn wait_for_vblank(&mut self) -> bool {
+        let must_wait = match self.quirks.display_wait {
+            DisplayWait::Off => false,
+            DisplayWait::PerDraw => true,
+            DisplayWait::PerFrame => !self.drawn_this_frame,
+        };
+
+        if must_wait && !self.vblank_released {
+            self.awaiting_vblank = true;
+            self.pc -= 2;
+            return true;
+        }
+
+        self.vblank_released = false;
+        self.drawn_this_frame = true;
+        false
+    }
+
+    fn draw_sprite(&mut self, vx: u16, vy: u16, num_rows: u16) -> Result<(), Chip8Error> {
+        if self.wait_for_vblank() {
+            return Ok(());
+        }
+
+        // In hires mode DXY0 is SUPER-CHIP's 16x16 sprite, two bytes per row
+        let (rows, sprite_width) = if num_rows == 0 && self.hires {
+            (16, 16)
+        } else {
+            (num_rows, 8)
+        };
+        let row_bytes = sprite_width as usize / 8;
+
+        self.check_bounds(self.i_reg as usize, rows as usize * row_bytes)?;
+
+        // Even a draw that changes nothing counts, e.g. XORing a sprite twice
+        self.display_dirty = true;
+
+        let (width, height) = self.display_size();
+        // The starting position always wraps; only pixels that then run
+        // past an edge count as wrapped
+        let x_coord = self.v_reg[vx as usize] as u16 % width as u16;
+        let y_coord = self.v_reg[vy as usize] as u16 % height as u16;
+        let mut collided_rows = 0;
+
+        for y_line in 0..rows {
+            let addr = self.i_reg as usize + y_line as usize * row_bytes;
+            let pixels = (0..row_bytes).fold(0u16, |pixels, byte| {
+                (pixels << 8) | self.ram[(addr + byte) % RAM_SIZE] as u16
+            });
+            let mut row_collided = false;
+
+            for x_line in 0..sprite_width {
+                if (pixels & (1 << (sprite_width - 1 - x_line))) != 0 {
+                    let x = (x_coord + x_line) as usize;
+                    let y = (y_coord + y_line) as usize;
+                    let wrapped = x >= width || y >= height;
+
+                    if wrapped && !self.quirks.sprite_wrapping {
+                        continue;
+                    }
+
+                    let idx = (x % width) + width * (y % height);
+
+                    if !wrapped || self.quirks.count_wrapped_collisions {
+                        row_collided |= self.screen[idx];
+                    }
+
+                    match self.quirks.draw_mode {
+                        DrawMode::Xor => self.screen[idx] ^= true,
+                        DrawMode::Or => self.screen[idx] = true,
+                    }
+                }
+            }
+
+            collided_rows += u8::from(row_collided);
+        }
+
+        // SUPER-CHIP counts the colliding rows of a 16x16 sprite
+        self.v_reg[0xF] = if sprite_width == 16 {
+            collided_rows
+        } else {
+            u8::from(collided_rows > 0)
+        };
+
+        if collided_rows > 0 {
+            if let Some(callback) = self.collision_callback.as_mut() {
+                callback(self.pc - 2);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Like the VIP's keypad latch, only the low nibble of VX picks the key
+    fn skip_if_key_pressed(&mut self, x: u16) {
+        let vx = self.v_reg[x as usize];
+        let key = self.keys[(vx & 0xF) as usize];
+
+        if key {
+            self.pc += 2;
+        }
+    }
+
+    fn skip_if_key_not_pressed(&mut self, x: u16) {
+        let vx = self.v_reg[x as usize];
+        let key = self.keys[(vx & 0xF) as usize];
+
+        if !key {
+            self.pc += 2;
+        }
+    }
+
+    fn assign_dt_to_vx(&mut self, x: u16) {
+        let x = x as usize;
+        self.v_reg[x] = self.delay_timer;
+    }
+
+    // When several keys are held at once the lowest key index always wins,
+    // so the result never depends on the order the frontend reported them in.
+    // With wait_for_key_release the key is only stored once it comes back up.
+    fn wait_for_key_press(&mut self, x: u16) {
+        let x = x as usize;
+        let pressed = self.keys.iter().position(|&pressed| pressed);
+
+        let key = if !self.quirks.wait_for_key_release {
+            pressed
+        } else {
+            match self.pressed_while_waiting {
+                Some(key) if !self.keys[key as usize] => Some(key as usize),
+                Some(_) => None,
+                None => {
+                    self.pressed_while_waiting = pressed.map(|key| key as u8);
+                    None
+                }
+            }
+        };
+
+        match key {
+            Some(key) => {
+                self.v_reg[x] = key as u8;
+                self.waiting_for_key = None;
+                self.pressed_while_waiting = None;
+            }
+            None => {
+                self.pc -= 2;
+                self.waiting_for_key = Some(x as u8);
+            }
+        }
+    }
+
+    fn assign_vx_to_dt(&mut self, x: u16) {
+        let vx = self.v_reg[x as usize];
+        self.delay_timer = vx;
+    }
+
+    fn assign_vx_to_st(&mut self, x: u16) {
+        let vx = self.v_reg[x as usize];
+        self.set_sound_timer(vx);
+    }
+
+    fn add_vx_to_ireg(&mut self, x: u16) {
+        let vx = self.v_reg[x as usize] as u16;
+        let sum = self.i_reg.wrapping_add(vx);
+
+        if self.quirks.i_overflow_sets_vf {
+            self.v_reg[0xF] = (sum > 0xFFF) as u8;
+        }
+
+        // Keep I on the 12-bit address bus so later accesses through it stay in RAM
+        self.i_reg = sum & 0xFFF;
+    }
+
+    fn assign_font_addr_to_ireg(&mut self, x: u16) {
+        let x = x as usize;
+        let c = self.v_reg[x] as u16;
+        self.i_reg = c * 5;
+    }
+
+    fn assign_vx_bcd_to_ireg(&mut self, x: u16) -> Result<(), Chip8Error> {
+        self.check_write(self.i_reg as usize, 3)?;
+
+        let i = self.i_reg as usize;
+        let vx = self.v_reg[x as usize];
+
+        let hundreds = vx / 100;
+        let tens = (vx / 10) % 10;
+        let ones = vx % 10;
+
+        self.ram[i % RAM_SIZE] = hundreds;
+        self.ram[(i + 1) % RAM_SIZE] = tens;
+        self.ram[(i + 2) % RAM_SIZE] = ones;
+
+        Ok(())
+    }
+
+    fn store_regs_into_ram(&mut self, x: u16) -> Result<(), Chip8Error> {
+        let x = x as usize;
+        let i = self.i_reg as usize;
+
+        self.check_write(i, x + 1)?;
+
+        for idx in 0..=x {
+            self.ram[(i + idx) % RAM_SIZE] = self.v_reg[idx];
+        }
+
+        self.apply_memory_increment(x);
+        Ok(())
+    }
+
+    fn load_ram_into_regs(&mut self, x: u16) -> Result<(), Chip8Error> {
+        let x = x as usize;
+        let i = self.i_reg as usize;
+
+        self.check_bounds(i, x + 1)?;
+
+        for idx in 0..=x {
+            self.v_reg[idx] = self.ram[(i + idx) % RAM_SIZE];
+        }
+
+        self.apply_memory_increment(x);
+        Ok(())
+    }
+
+    fn apply_memory_increment(&mut self, x: usize) {
+        let increment = match self.quirks.memory_increment {
+            MemoryIncrement::Unchanged => 0,
+            MemoryIncrement::ByX => x as u16,
+            MemoryIncrement::ByXPlusOne => x as u16 + 1,
+        };
+
+        self.i_reg = self.i_reg.wrapping_add(increment) & 0xFFF;
+    }
+
+    #[cfg(feature = "debug_opcodes")]
+    fn debug_dump_regs(&self, n: u16) {
+        eprintln!("{}", self.debug_regs_line(n));
+    }
+
+    #[cfg(feature = "debug_opcodes")]
+    fn debug_regs_line(&self, n: u16) -> String {
+        let regs: Vec<String> = self.v_reg[..=n as usize]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("V{:X}={:02X}", i, v))
+            .collect();
+
+        format!(
+            "[0x{:03X}] {} I={:03X}",
+            self.pc - 2,
+            regs.join(" "),
+            self.i_reg
+        )
+    }
+
+    // Without the feature 0FFN stays a NOP so instrumented ROMs still run
+    #[cfg(not(feature = "debug_opcodes"))]
+    fn debug_dump_regs(&self, _n: u16) {}
+
+    fn unknown_opcode(&mut self, op: u16) -> Result<(), Chip8Error> {
+        if self.unknown_opcode_policy == UnknownOpcodePolicy::Panic {
+            panic!("unknown opcode {:#06x} at {:#05x}", op, self.pc - 2);
+        }
+
+        *self.unknown_opcodes.entry(op).or_insert(0) += 1;
+
+        match self.unknown_opcode_policy {
+            UnknownOpcodePolicy::Error => Err(Chip8Error::UnknownOpcode {
+                op,
+                pc: self.pc - 2,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn execute(&mut self, op: u16) -> Result<(), Chip8Error> {
+        if let Some(mut hook) = self.opcode_override.take() {
+            let handled = hook(self, op);
+
+            // Put the hook back unless it installed a replacement while it ran
+            if self.opcode_override.is_none() {
+                self.opcode_override = Some(hook);
+            }
+
+            if handled {
+                return Ok(());
+            }
+        }
+
+        match Instruction::decode(op) {
+            Instruction::Nop => (),
+            Instruction::Cls => self.clear_screen(),
+            Instruction::Ret => self.end_subroutine()?,
+            Instruction::ScrollDown { n } => self.scroll_down(n.into()),
+            Instruction::ScrollRight => self.scroll_right(),
+            Instruction::ScrollLeft => self.scroll_left(),
+            Instruction::Exit => self.halted = true,
+            Instruction::LowRes => self.set_hires(false),
+            Instruction::HighRes => self.set_hires(true),
+            Instruction::Debug { n } => self.debug_dump_regs(n.into()),
+            Instruction::Jump { nnn } => self.jump(nnn),
+            Instruction::Call { nnn } => self.call_subroutine(nnn)?,
+            Instruction::SkipEqByte { x, nn } => self.skip_if_vx_equals_nn(x.into(), nn.into()),
+            Instruction::SkipNotEqByte { x, nn } => {
+                self.skip_if_vx_not_equals_nn(x.into(), nn.into())
+            }
+            Instruction::SkipEqReg { x, y } => self.skip_if_vx_equals_vy(x.into(), y.into()),
+            Instruction::LoadByte { x, nn } => self.assign_nn_to_vx(x.into(), nn.into()),
+            Instruction::AddByte { x, nn } => self.add_nn_to_vx(x.into(), nn.into()),
+            Instruction::LoadReg { x, y } => self.assign_vx_to_vy(x.into(), y.into()),
+            Instruction::Or { x, y } => self.vx_or_vy(x.into(), y.into()),
+            Instruction::And { x, y } => self.vx_and_vy(x.into(), y.into()),
+            Instruction::Xor { x, y } => self.vx_xor_vy(x.into(), y.into()),
+            Instruction::AddReg { x, y } => self.add_vy_to_vx(x.into(), y.into()),
+            Instruction::Sub { x, y } => self.sub_vy_from_vx(x.into(), y.into()),
+            Instruction::ShiftRight { x, y } => self.rshift_vx(x.into(), y.into()),
+            Instruction::SubN { x, y } => self.sub_vx_from_vy(x.into(), y.into()),
+            Instruction::ShiftLeft { x, y } => self.lshift_vx(x.into(), y.into()),
+            Instruction::SkipNotEqReg { x, y } => self.skip_if_vx_not_equals_vy(x.into(), y.into()),
+            Instruction::LoadIndex { nnn } => self.assign_nnn_to_ireg(nnn),
+            Instruction::JumpOffset { x, nnn } => self.jump_to_offset(x.into(), nnn),
+            Instruction::Random { x, nn } => self.assign_rand_and_nn_to_vx(x.into(), nn.into()),
+            Instruction::Draw { x, y, n } => self.draw_sprite(x.into(), y.into(), n.into())?,
+            Instruction::SkipKeyPressed { x } => self.skip_if_key_pressed(x.into()),
+            Instruction::SkipKeyNotPressed { x } => self.skip_if_key_not_pressed(x.into()),
+            Instruction::LoadFromDelay { x } => self.assign_dt_to_vx(x.into()),
+            Instruction::WaitKey { x } => self.wait_for_key_press(x.into()),
+            Instruction::LoadDelay { x } => self.assign_vx_to_dt(x.into()),
+            Instruction::LoadSound { x } => self.assign_vx_to_st(x.into()),
+            Instruction::AddIndex { x } => self.add_vx_to_ireg(x.into()),
+            Instruction::LoadFont { x } => self.assign_font_addr_to_ireg(x.into()),
+            Instruction::Bcd { x } => self.assign_vx_bcd_to_ireg(x.into())?,
+            Instruction::StoreRegs { x } => self.store_regs_into_ram(x.into())?,
+            Instruction::LoadRegs { x } => self.load_ram_into_regs(x.into())?,
+            Instruction::Unknown(op) => self.unknown_opcode(op)?,
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mem_slice_rejects_ranges_past_ram() {
+        let emu = Emulator::new();
+
+        assert_eq!(emu.mem_slice(0x0FFE, 2).map(<[u8]>::len), Some(2));
+        assert_eq!(emu.mem_slice(0x0FFE, 3), None);
+        assert_eq!(emu.mem_slice(0x0FFF, usize::MAX), None);
+    }
+
+    #[test]
+    fn load_into_ram_rejects_data_like_load() {
+        let max = RAM_SIZE - START_ADDR as usize;
+        let rom = vec![0xAB; max + 1];
+        let mut loaded = Emulator::new();
+        let mut copied = Emulator::new();
+
+        assert_eq!(
+            loaded.load(&rom),
+            Err(Chip8Error::RomTooLarge { size: max + 1, max })
+        );
+        assert_eq!(
+            copied.load_into_ram(&rom, START_ADDR),
+            Err(Chip8Error::RomTooLarge { size: max + 1, max })
+        );
+        assert_eq!(
+            copied.mem_slice(START_ADDR, max),
+            loaded.mem_slice(START_ADDR, max)
+        );
+
+        assert_eq!(loaded.load(&rom[..max]), Ok(()));
+        assert_eq!(copied.load_into_ram(&rom[..max], START_ADDR), Ok(()));
+        assert_eq!(
+            copied.mem_slice(START_ADDR, max),
+            loaded.mem_slice(START_ADDR, max)
+        );
+    }
+
+    #[test]
+    fn load_into_ram_checks_the_address() {
+        let mut emu = Emulator::new();
+
+        assert_eq!(emu.load_into_ram(&[1, 2], 0x0FFE), Ok(()));
+        assert_eq!(emu.mem_slice(0x0FFE, 2), Some(&[1, 2][..]));
+        assert_eq!(
+            emu.load_into_ram(&[1, 2], 0x0FFF),
+            Err(Chip8Error::RomTooLarge { size: 2, max: 1 })
+        );
+        assert_eq!(
+            emu.load_into_ram(&[], 0x1000),
+            Err(Chip8Error::InvalidAddress { addr: 0x1000 })
+        );
+    }
+
+    #[test]
+    fn run_program_runs_each_opcode() {
+        let mut emu = Emulator::new();
+
+        // LD V0, 5; LD V1, 7; ADD V0, V1; LD I, 0x300
+        emu.run_program(&[0x6005, 0x6107, 0x8014, 0xA300]).unwrap();
+
+        assert_eq!(emu.v_reg[0], 12);
+        assert_eq!(emu.v_reg[1], 7);
+        assert_eq!(emu.i_reg, 0x300);
+        assert_eq!(emu.pc, 0x208);
+    }
+
+    #[test]
+    fn run_program_stops_when_jumping_out() {
+        let mut emu = Emulator::new();
+
+        // JP 0x300; LD V0, 1
+        emu.run_program(&[0x1300, 0x6001]).unwrap();
+
+        assert_eq!(emu.pc, 0x300);
+        assert_eq!(emu.v_reg[0], 0);
+    }
+
+    #[test]
+    fn run_program_rejects_programs_larger_than_ram() {
+        let max = RAM_SIZE - START_ADDR as usize;
+        let mut emu = Emulator::new();
+
+        assert_eq!(
+            emu.run_program(&vec![0x0000; max / 2 + 1]),
+            Err(Chip8Error::RomTooLarge { size: max + 2, max })
+        );
+        assert_eq!(emu.pc, START_ADDR);
+        assert_eq!(emu.cycle_count(), 0);
+    }
+
+    #[test]
+    fn restore_tolerates_a_bad_stack_pointer_and_screen() {
+        let mut emu = Emulator::new();
+        let mut state = emu.state();
+        state.stack_ptr = 0xFFFF;
+        state.screen = vec![true; 10];
+
+        emu.restore(&state);
+
+        assert_eq!(emu.register_snapshot().stack.len(), STACK_SIZE);
+        assert!(emu.screen[..10].iter().all(|&pixel| pixel));
+        assert!(emu.screen[10..].iter().all(|&pixel| !pixel));
+    }
+
+    fn draw_row_at(emu: &mut Emulator, x: u8, lit: (usize, usize)) -> u8 {
+        let (width, _) = emu.display_size();
+        emu.screen[lit.0 + width * lit.1] = true;
+        emu.ram[0x300] = 0xFF;
+        emu.v_reg[0] = x;
+        emu.v_reg[1] = 0;
+
+        // LD I, 0x300; DRW V0, V1, 1
+        emu.run_program(&[0xA300, 0xD011]).unwrap();
+        emu.v_reg[0xF]
+    }
+
+    #[test]
+    fn wrapped_pixel_collisions_follow_the_quirk() {
+        for count_wrapped_collisions in [true, false] {
+            let mut emu = Emulator::new();
+            emu.quirks.count_wrapped_collisions = count_wrapped_collisions;
+
+            // Pixels 60-63 stay on screen and 64-67 wrap onto 0-3
+            let vf = draw_row_at(&mut emu, 60, (1, 0));
+
+            assert_eq!(vf, u8::from(count_wrapped_collisions));
+            assert!(!emu.screen[1]);
+        }
+    }
+
+    #[test]
+    fn off_screen_start_wraps_before_collisions_are_counted() {
+        for count_wrapped_collisions in [true, false] {
+            let mut emu = Emulator::new();
+            emu.quirks.count_wrapped_collisions = count_wrapped_collisions;
+
+            // VX = 66 starts at column 2, so nothing in the row crosses an edge
+            let vf = draw_row_at(&mut emu, 66, (5, 0));
+
+            assert_eq!(vf, 1);
+            assert!(!emu.screen[5]);
+        }
+    }
+
+    #[test]
+    fn off_screen_start_wraps_when_clipping() {
+        let mut emu = Emulator::new();
+        emu.quirks.sprite_wrapping = false;
+
+        let vf = draw_row_at(&mut emu, 66, (5, 0));
+
+        assert_eq!(vf, 1);
+        assert!(emu.screen[2..10]
+            .iter()
+            .enumerate()
+            .all(|(i, &p)| p == (i != 3)));
+    }
+
+    #[test]
+    fn sprite_reads_past_ram_wrap_or_fail() {
+        let mut emu = Emulator::new();
+        emu.ram[0x0FFF] = 0x80;
+        emu.ram[0] = 0x80;
+
+        // LD I, 0xFFF; DRW V0, V1, 2
+        emu.run_program(&[0xAFFF, 0xD012]).unwrap();
+        assert!(emu.screen[0]);
+        assert!(emu.screen[emu.display_size().0]);
+
+        let mut emu = Emulator::new();
+        emu.set_strict(true);
+
+        assert_eq!(
+            emu.run_program(&[0xAFFF, 0xD012]),
+            Err(Chip8Error::MemoryOutOfBounds {
+                addr: RAM_SIZE,
+                pc: 0x202
+            })
+        );
+        assert!(emu.screen.iter().all(|&pixel| !pixel));
+    }
+
+    #[test]
+    fn stores_and_loads_near_the_top_of_ram() {
+        let mut emu = Emulator::new();
+        emu.v_reg = core::array::from_fn(|idx| idx as u8 + 1);
+
+        // LD I, 0xFF8; LD [I], VF
+        emu.run_program(&[0xAFF8, 0xFF55]).unwrap();
+        assert_eq!(
+            emu.mem_slice(0x0FF8, 8),
+            Some(&[1, 2, 3, 4, 5, 6, 7, 8][..])
+        );
+        assert_eq!(
+            emu.mem_slice(0, 8),
+            Some(&[9, 10, 11, 12, 13, 14, 15, 16][..])
+        );
+
+        emu.v_reg = [0; REGISTER_COUNT];
+        // LD I, 0xFF8; LD VF, [I]
+        emu.run_program(&[0xAFF8, 0xFF65]).unwrap();
+        assert_eq!(emu.v_reg[0], 1);
+        assert_eq!(emu.v_reg[0xF], 16);
+
+        let mut emu = Emulator::new();
+        emu.set_strict(true);
+
+        for op in [0xFF55, 0xFF65, 0xDFF9] {
+            assert_eq!(
+                emu.run_program(&[0xAFF8, op]),
+                Err(Chip8Error::MemoryOutOfBounds {
+                    addr: RAM_SIZE,
+                    pc: 0x202
+                })
+            );
+        }
+        assert_eq!(emu.mem_slice(0, 8), Emulator::new().mem_slice(0, 8));
+    }
+
+    #[test]
+    fn add_to_i_at_the_top_of_ram() {
+        for i_overflow_sets_vf in [false, true] {
+            let mut emu = Emulator::new();
+            emu.quirks.i_overflow_sets_vf = i_overflow_sets_vf;
+            emu.v_reg[0xF] = 0xAA;
+
+            // LD I, 0xFFE; LD V0, 1; ADD I, V0
+            emu.run_program(&[0xAFFE, 0x6001, 0xF01E]).unwrap();
+            assert_eq!(emu.i_reg, 0x0FFF);
+            assert_eq!(emu.v_reg[0xF], if i_overflow_sets_vf { 0 } else { 0xAA });
+
+            // ADD I, V0
+            emu.run_program(&[0xF01E]).unwrap();
+            assert_eq!(emu.i_reg, 0x000);
+            assert_eq!(emu.v_reg[0xF], if i_overflow_sets_vf { 1 } else { 0xAA });
+        }
+    }
+
+    #[test]
+    fn i_stays_in_ram_after_overflowing() {
+        let mut emu = Emulator::new();
+        emu.set_strict(true);
+
+        // LD I, 0xFFF; LD V0, 0xFF; ADD I, V0; DRW V0, V1, 15
+        emu.run_program(&[0xAFFF, 0x60FF, 0xF01E, 0xD01F]).unwrap();
+        assert_eq!(emu.i_reg, 0x0FE);
+
+        let mut emu = Emulator::new();
+        emu.quirks.memory_increment = MemoryIncrement::ByXPlusOne;
+
+        // LD I, 0xFF8; LD [I], VF; DRW V0, V1, 15
+        emu.run_program(&[0xAFF8, 0xFF55, 0xD01F]).unwrap();
+        assert_eq!(emu.i_reg, 0x008);
+    }
+
+    #[test]
+    fn key_skips_use_the_low_nibble_of_vx() {
+        let mut emu = Emulator::new();
+        emu.keypress(0x5, true);
+
+        // LD V0, 0xF5; SKP V0
+        emu.run_program(&[0x60F5, 0xE09E]).unwrap();
+        assert_eq!(emu.pc, 0x206);
+
+        // LD V0, 0xFF; SKNP V0
+        emu.run_program(&[0x60FF, 0xE0A1]).unwrap();
+        assert_eq!(emu.pc, 0x206);
+    }
+
+    #[test]
+    fn malicious_rom_is_reported_not_panicking() {
+        let store_past_ram = [0xAF, 0xFF, 0xFF, 0x55];
+        let draw_past_ram = [0xAF, 0xFF, 0xDF, 0xFF];
+        let stack_overflow = [0x22, 0x00];
+        let unknown_opcode = [0xE0, 0x00];
+
+        for (rom, expected) in [
+            (
+                &store_past_ram[..],
+                Chip8Error::MemoryOutOfBounds {
+                    addr: RAM_SIZE,
+                    pc: 0x202,
+                },
+            ),
+            (
+                &draw_past_ram[..],
+                Chip8Error::MemoryOutOfBounds {
+                    addr: RAM_SIZE,
+                    pc: 0x202,
+                },
+            ),
+            (&stack_overflow[..], Chip8Error::StackOverflow { pc: 0x200 }),
+            (
+                &unknown_opcode[..],
+                Chip8Error::UnknownOpcode {
+                    op: 0xE000,
+                    pc: 0x200,
+                },
+            ),
+        ] {
+            let mut emu = Emulator::new();
+            emu.set_strict(true);
+            emu.set_unknown_opcode_policy(UnknownOpcodePolicy::Error);
+            emu.load(rom).unwrap();
+
+            assert_eq!(emu.run_cycles(100), Err(expected));
+        }
+    }
+
+    #[test]
+    fn random_bytes_never_panic() {
+        let mut rng = rng::Xorshift32::new(1);
+
+        for _ in 0..200 {
+            let rom: Vec<u8> = (0..256).map(|_| rng.next_u32() as u8).collect();
+
+            for strict in [false, true] {
+                let mut emu = Emulator::new();
+                emu.set_strict(strict);
+                emu.set_unknown_opcode_policy(UnknownOpcodePolicy::Nop);
+                emu.load(&rom).unwrap();
+                let _ = emu.run_cycles(1000);
+            }
+        }
+    }
+
+    #[test]
+    fn hires_dxy0_draws_a_16x16_sprite() {
+        let mut emu = Emulator::new();
+        emu.ram[0x300..0x320].fill(0xFF);
+
+        // HIGH; LD I, 0x300; DRW V0, V1, 0
+        emu.run_program(&[0x00FF, 0xA300, 0xD010]).unwrap();
+
+        let (width, _) = emu.display_size();
+        let lit: Vec<(usize, usize)> = (0..emu.screen.len())
+            .filter(|&idx| emu.screen[idx])
+            .map(|idx| (idx % width, idx / width))
+            .collect();
+
+        assert_eq!(lit.len(), 16 * 16);
+        assert!(lit.iter().all(|&(x, y)| x < 16 && y < 16));
+        assert_eq!(emu.v_reg[0xF], 0);
+    }
+
+    #[test]
+    fn hires_dxy0_counts_colliding_rows() {
+        let mut emu = Emulator::new();
+        emu.ram[0x300..0x320].fill(0xFF);
+
+        // HIGH; LD I, 0x300; DRW V0, V1, 0
+        emu.run_program(&[0x00FF, 0xA300, 0xD010]).unwrap();
+
+        // Clear rows 3-7 and 10 so only 10 rows collide on the second draw
+        let (width, _) = emu.display_size();
+        for y in [3, 4, 5, 6, 7, 10] {
+            emu.screen[y * width..y * width + 16].fill(false);
+        }
+
+        // DRW V0, V1, 0
+        emu.run_program(&[0xD010]).unwrap();
+        assert_eq!(emu.v_reg[0xF], 10);
+    }
+
+    #[test]
+    fn lores_dxy0_draws_nothing() {
+        let mut emu = Emulator::new();
+        emu.ram[0x300..0x320].fill(0xFF);
+
+        // LD I, 0x300; DRW V0, V1, 0
+        emu.run_program(&[0xA300, 0xD010]).unwrap();
+
+        assert!(emu.screen.iter().all(|&pixel| !pixel));
+    }
+
+    #[test]
+    fn decode_at_reads_live_ram() {
+        let mut emu = Emulator::new();
+        emu.load(&[0xD1, 0x25]).unwrap();
+
+        assert_eq!(emu.decode_at(0x200).as_deref(), Some("DRW V1, V2, 5"));
+
+        emu.write_byte(0x200, 0x00).unwrap();
+        emu.write_byte(0x201, 0xE0).unwrap();
+        assert_eq!(emu.decode_at(0x200).as_deref(), Some("CLS"));
+        assert_eq!(emu.decode_at(0x0FFF), None);
+    }
+
+    #[test]
+    fn protected_program_region_rejects_stores() {
+        // LD I, 0x200; LD [I], V1
+        let rom = [0xA2, 0x00, 0xF1, 0x55];
+        let mut emu = Emulator::new();
+        emu.load(&rom).unwrap();
+        emu.protect_range(0x200, 0x200 + rom.len() as u16);
+        emu.v_reg[0] = 0xAA;
+
+        assert_eq!(
+            emu.run_cycles(2),
+            Err(Chip8Error::ProtectedWrite {
+                addr: 0x200,
+                pc: 0x202
+            })
+        );
+        assert_eq!(emu.mem_slice(0x200, 4), Some(&rom[..]));
+
+        emu.clear_protection();
+        emu.pc = 0x202;
+        emu.run_cycles(1).unwrap();
+        assert_eq!(emu.read_byte(0x200), Some(0xAA));
+    }
+
+    #[test]
+    fn tick_with_input_samples_keys_before_each_instruction() {
+        // LD V0, 7; SKP V0; JP 0x202; LD V1, 1
+        let mut emu = Emulator::new();
+        emu.load(&[0x60, 0x07, 0xE0, 0x9E, 0x12, 0x02, 0x61, 0x01])
+            .unwrap();
+
+        let mut polls = 0;
+        let mut poll = || {
+            polls += 1;
+            let mut keys = [false; NUM_KEYS];
+            keys[7] = polls == 6;
+            keys
+        };
+
+        for _ in 0..5 {
+            emu.tick_with_input(&mut poll).unwrap();
+            assert_ne!(emu.pc, 0x206);
+        }
+
+        // The sixth poll holds key 7 just as SKP V0 runs
+        emu.tick_with_input(&mut poll).unwrap();
+        assert_eq!(emu.pc, 0x206);
+    }
+
+    #[test]
+    fn jump_with_offset_wraps_into_ram() {
+        let mut emu = Emulator::new();
+        emu.v_reg[0] = 0xFF;
+
+        // JP V0, 0xFFF
+        emu.run_program(&[0xBFFF]).unwrap();
+        assert_eq!(emu.pc, 0x0FE);
+
+        let mut emu = Emulator::new();
+        emu.quirks.jump_uses_vx = true;
+        emu.v_reg[0xF] = 0xFF;
+
+        emu.run_program(&[0xBFFF]).unwrap();
+        assert_eq!(emu.pc, 0x0FE);
+    }
+
+    #[test]
+    fn debug_opcode_leaves_state_alone() {
+        let mut emu = Emulator::new();
+        // LD V0, 0x12; LD V1, 0x34; LD I, 0x345; DBG V2
+        emu.load(&[0x60, 0x12, 0x61, 0x34, 0xA3, 0x45, 0x0F, 0xF2])
+            .unwrap();
+        emu.run_cycles(3).unwrap();
+        let before = emu.state();
+
+        emu.tick().unwrap();
+
+        assert_eq!(
+            emu.state(),
+            EmulatorState {
+                pc: 0x208,
+                ..before
+            }
+        );
+    }
+
+    #[cfg(feature = "debug_opcodes")]
+    #[test]
+    fn debug_opcode_logs_registers() {
+        let mut emu = Emulator::new();
+        // LD V0, 0x12; LD V1, 0x34; LD I, 0x345; DBG V2
+        emu.run_program(&[0x6012, 0x6134, 0xA345, 0x0FF2]).unwrap();
+
+        assert_eq!(emu.debug_regs_line(2), "[0x206] V0=12 V1=34 V2=00 I=345");
+    }
+
+    #[test]
+    fn clear_display_keeps_the_cpu_state() {
+        let mut emu = Emulator::new();
+        // LD V0, 5; LD F, V0; DRW V0, V0, 5
+        emu.run_program(&[0x6005, 0xF029, 0xD005]).unwrap();
+        assert!(emu.screen.iter().any(|&pixel| pixel));
+        emu.take_display_dirty();
+        let regs = emu.register_snapshot();
+
+        emu.clear_display();
+
+        assert!(emu.screen.iter().all(|&pixel| !pixel));
+        assert!(emu.take_display_dirty());
+        assert_eq!(emu.register_snapshot(), regs);
+    }
+
+    #[test]
+    fn key_wait_latches_the_lowest_key() {
+        let mut emu = Emulator::new();
+        emu.keypress(5, true);
+        emu.keypress(2, true);
+
+        // LD V3, K
+        emu.run_program(&[0xF30A]).unwrap();
+        assert_eq!(emu.v_reg[3], 2);
+
+        let mut emu = Emulator::new();
+        emu.quirks.wait_for_key_release = true;
+        emu.load(&[0xF3, 0x0A]).unwrap();
+        emu.keypress(5, true);
+        emu.keypress(2, true);
+
+        emu.tick().unwrap();
+        assert!(emu.is_waiting_for_key());
+
+        // Key 5 is still held, but the wait is for key 2
+        emu.keypress(2, false);
+        emu.tick().unwrap();
+        assert!(!emu.is_waiting_for_key());
+        assert_eq!(emu.v_reg[3], 2);
+        assert_eq!(emu.pc, 0x202);
+    }
+
+    #[test]
+    fn memory_increment_modes() {
+        for (mode, i_after) in [
+            (MemoryIncrement::Unchanged, 0x300),
+            (MemoryIncrement::ByX, 0x303),
+            (MemoryIncrement::ByXPlusOne, 0x304),
+        ] {
+            for op in [0xF355, 0xF365] {
+                let mut emu = Emulator::new();
+                emu.quirks.memory_increment = mode;
+
+                // LD I, 0x300; LD [I], V3 or LD V3, [I]
+                emu.run_program(&[0xA300, op]).unwrap();
+                assert_eq!(emu.i_reg, i_after, "{:?} {:04X}", mode, op);
+            }
+        }
+    }
+
+    #[test]
+    fn opcode_override_replaces_random() {
+        let mut emu = Emulator::new();
+        emu.set_opcode_override(Box::new(|emu, op| {
+            if op & 0xF000 != 0xC000 {
+                return false;
+            }
+
+            let x = ((op & 0x0F00) >> 8) as usize;
+            emu.v_reg[x] = 0x42 & op as u8;
+            true
+        }));
+
+        // RND V1, 0xFF; RND V2, 0x0F; LD V3, 0x99
+        emu.run_program(&[0xC1FF, 0xC20F, 0x6399]).unwrap();
+
+        assert_eq!(emu.v_reg[1], 0x42);
+        assert_eq!(emu.v_reg[2], 0x02);
+        assert_eq!(emu.v_reg[3], 0x99);
+    }
+
+    #[test]
+    fn apply_patch_overwrites_loaded_bytes() {
+        let mut emu = Emulator::new();
+        emu.load(&[0x60, 0x01, 0x12, 0x02]).unwrap();
+
+        emu.apply_patch(&[(0x201, 0x09), (0x203, 0x00)]).unwrap();
+        assert_eq!(emu.mem_slice(0x200, 4), Some(&[0x60, 0x09, 0x12, 0x00][..]));
+
+        assert_eq!(
+            emu.apply_patch(&[(0x200, 0xFF), (0x1000, 0xFF)]),
+            Err(Chip8Error::InvalidAddress { addr: 0x1000 })
+        );
+        assert_eq!(emu.read_byte(0x200), Some(0x60));
+    }
+
+    #[test]
+    fn emulated_time_counts_timer_ticks() {
+        let mut emu = Emulator::new();
+
+        for _ in 0..120 {
+            emu.tick_timers();
+        }
+        assert_eq!(emu.emulated_time(), Duration::from_secs(2));
+
+        emu.tick_timers();
+        assert!(emu.emulated_time() > Duration::from_secs(2));
+
+        emu.reset();
+        assert_eq!(emu.emulated_time(), Duration::ZERO);
+    }
+
+    #[test]
+    fn detects_the_delay_timer_spin_loop() {
+        // LD V0, 30; LD DT, V0; then LD V1, DT; SE V1, 0; JP 0x204
+        let rom = [0x60, 0x1E, 0xF0, 0x15, 0xF1, 0x07, 0x31, 0x00, 0x12, 0x04];
+        let mut emu = Emulator::new();
+        emu.load(&rom).unwrap();
+
+        emu.run_cycles(1).unwrap();
+        assert!(!emu.detect_delay_spin());
+
+        emu.run_cycles(1).unwrap();
+        for _ in 0..3 {
+            assert!(emu.detect_delay_spin());
+            emu.tick().unwrap();
+        }
+
+        emu.delay_timer = 0;
+        assert!(!emu.detect_delay_spin());
+
+        // The skip tests a different register than the one read
+        let mut emu = Emulator::new();
+        emu.load(&[0xF1, 0x07, 0x32, 0x00, 0x12, 0x00]).unwrap();
+        emu.delay_timer = 30;
+        assert!(!emu.detect_delay_spin());
+    }
+
+    #[test]
+    fn key_hint_only_while_waiting() {
+        let mut emu = Emulator::new();
+        // LD V4, K; LD V0, 1
+        emu.load(&[0xF4, 0x0A, 0x60, 0x01]).unwrap();
+        assert_eq!(emu.expected_key_hint(), None);
+
+        emu.tick().unwrap();
+        assert_eq!(emu.expected_key_hint(), Some(4));
+
+        emu.keypress(0xB, true);
+        emu.tick().unwrap();
+        assert_eq!(emu.expected_key_hint(), None);
+        assert_eq!(emu.v_reg[4], 0xB);
+    }
+
+    fn draws_per_frame(display_wait: DisplayWait) -> Vec<u8> {
+        let mut emu = Emulator::new();
+        emu.quirks.display_wait = display_wait;
+        // DRW V0, V0, 1; ADD V1, 1; JP 0x200
+        emu.load(&[0xD0, 0x01, 0x71, 0x01, 0x12, 0x00]).unwrap();
+
+        (0..4)
+            .map(|_| {
+                let before = emu.v_reg[1];
+                for _ in 0..30 {
+                    emu.tick().unwrap();
+                }
+                emu.tick_timers();
+                emu.v_reg[1] - before
+            })
+            .collect()
+    }
+
+    #[test]
+    fn display_wait_modes_limit_draws_per_frame() {
+        assert_eq!(draws_per_frame(DisplayWait::Off), [10, 10, 10, 10]);
+        assert_eq!(draws_per_frame(DisplayWait::PerDraw), [0, 1, 1, 1]);
+        // The draw released by a vblank is that frame's first, so the rest
+        // go straight through until the next frame's first draw stalls
+        assert_eq!(draws_per_frame(DisplayWait::PerFrame), [0, 10, 0, 10]);
+    }
+
+    #[test]
+    fn run_until_a_register_value() {
+        let mut emu = Emulator::new();
+        // ADD V0, 3; JP 0x200
+        emu.load(&[0x70, 0x03, 0x12, 0x00]).unwrap();
+
+        assert_eq!(
+            emu.run_until(|emu| emu.registers()[0] == 30, 1_000),
+            Ok(true)
+        );
+        assert_eq!(emu.registers()[0], 30);
+        assert_eq!(emu.cycle_count(), 19);
+
+        assert_eq!(
+            emu.run_until(|emu| emu.registers()[0] == 31, 100),
+            Ok(false)
+        );
+        assert_eq!(emu.cycle_count(), 119);
+    }
+
+    #[test]
+    fn collision_callback_gets_the_drawing_pc() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let hits = Rc::new(RefCell::new(Vec::new()));
+        let mut emu = Emulator::new();
+        let log = Rc::clone(&hits);
+        emu.set_collision_callback(Box::new(move |pc| log.borrow_mut().push(pc)));
+
+        // LD F, V0, then the same sprite four times, every other draw
+        // landing on the one before it
+        emu.run_program(&[0xF029, 0xD005, 0xD005, 0xD005, 0xD005])
+            .unwrap();
+
+        assert_eq!(*hits.borrow(), [0x204, 0x208]);
+    }
+
+    #[test]
+    fn load_truncating_reports_dropped_bytes() {
+        let max = RAM_SIZE - START_ADDR as usize;
+        let rom: Vec<u8> = (0..max + 100).map(|i| i as u8).collect();
+        let mut emu = Emulator::new();
+
+        assert_eq!(emu.load_truncating(&rom), 100);
+        assert_eq!(emu.mem_slice(START_ADDR, max), Some(&rom[..max]));
+        assert_eq!(emu.load_truncating(&rom[..10]), 0);
+        assert!(emu.load(&rom).is_err());
+    }
+
+    #[test]
+    fn draw_modes_on_a_repeated_sprite() {
+        for (mode, lit) in [(DrawMode::Xor, false), (DrawMode::Or, true)] {
+            let mut emu = Emulator::new();
+            emu.quirks.draw_mode = mode;
+
+            // LD F, V0; DRW V0, V0, 5; DRW V0, V0, 5
+            emu.run_program(&[0xF029, 0xD005, 0xD005]).unwrap();
+
+            assert_eq!(emu.screen[0], lit, "{:?}", mode);
+            assert_eq!(emu.v_reg[0xF], 1, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn breakpoint_stops_exactly_at_its_address() {
+        let mut emu = Emulator::new();
+        // LD V0, 1; LD V1, 2; LD V2, 3; JP 0x200
+        emu.load(&[0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x12, 0x00])
+            .unwrap();
+        emu.add_breakpoint(0x204);
+
+        assert_eq!(emu.run_until(Emulator::at_breakpoint, 100), Ok(true));
+        assert_eq!(emu.pc, 0x204);
+        assert_eq!(emu.registers()[..3], [1, 2, 0]);
+
+        emu.tick().unwrap();
+        assert_eq!(emu.run_until(Emulator::at_breakpoint, 100), Ok(true));
+        assert_eq!(emu.cycle_count(), 6);
+
+        emu.remove_breakpoint(0x204);
+        assert_eq!(emu.run_until(Emulator::at_breakpoint, 100), Ok(false));
+    }
+
+    #[test]
+    fn trace_only_fires_inside_the_range() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let traced = Rc::new(RefCell::new(Vec::new()));
+        let mut emu = Emulator::new();
+        let log = Rc::clone(&traced);
+        emu.set_trace_callback(Box::new(move |pc, op| log.borrow_mut().push((pc, op))));
+        emu.set_trace_range(0x204, 0x208);
+
+        // LD V0, 1; LD V1, 2; LD V2, 3; LD V3, 4; LD V4, 5
+        emu.run_program(&[0x6001, 0x6102, 0x6203, 0x6304, 0x6405])
+            .unwrap();
+
+        assert_eq!(*traced.borrow(), [(0x204, 0x6203), (0x206, 0x6304)]);
+    }
+
+    #[test]
+    fn bcd_of_every_byte() {
+        let mut emu = Emulator::new();
+
+        for value in 0..=255u8 {
+            emu.v_reg[5] = value;
+            // LD I, 0x300; LD B, V5
+            emu.run_program(&[0xA300, 0xF533]).unwrap();
+
+            let expected = [value / 100, value / 10 % 10, value % 10];
+            assert_eq!(emu.mem_slice(0x300, 3), Some(&expected[..]), "{}", value);
+        }
+    }
+
+    #[test]
+    fn history_capacity_grows_and_shrinks() {
+        let mut emu = Emulator::new();
+        // ADD V0, 1; JP 0x200
+        emu.load(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        emu.enable_history(3);
+
+        let frame = |emu: &mut Emulator| {
+            emu.tick().unwrap();
+            emu.tick().unwrap();
+            emu.tick_timers();
+        };
+
+        for _ in 0..5 {
+            frame(&mut emu);
+        }
+        assert_eq!(emu.history_len(), 3);
+
+        emu.set_history_capacity(6);
+        for _ in 0..5 {
+            frame(&mut emu);
+        }
+        assert_eq!(emu.history_len(), 6);
+
+        // Shrinking keeps the newest frames
+        emu.set_history_capacity(2);
+        assert_eq!(emu.history_len(), 2);
+        assert!(emu.rewind());
+        assert_eq!(emu.registers()[0], 10);
+        assert!(emu.rewind());
+        assert_eq!(emu.registers()[0], 9);
+
+        emu.set_history_capacity(0);
+        frame(&mut emu);
+        assert_eq!(emu.history_len(), 0);
+        assert!(!emu.rewind());
+    }
+
+    #[test]
+    fn run_cycles_ticks_timers_every_tenth_instruction() {
+        let mut emu = Emulator::new();
+        // LD V0, 5; LD DT, V0; JP 0x204
+        emu.load(&[0x60, 0x05, 0xF0, 0x15, 0x12, 0x04]).unwrap();
+
+        emu.run_cycles(9).unwrap();
+        assert_eq!(emu.delay_timer(), 5);
+
+        emu.run_cycles(1).unwrap();
+        assert_eq!(emu.delay_timer(), 4);
+
+        emu.run_cycles(25).unwrap();
+        assert_eq!(emu.cycle_count(), 35);
+        assert_eq!(emu.delay_timer(), 2);
+    }
+
+    #[test]
+    fn run_cycles_stops_at_the_first_error() {
+        let mut emu = Emulator::new();
+        // LD V0, 1; RET
+        emu.load(&[0x60, 0x01, 0x00, 0xEE]).unwrap();
+
+        assert_eq!(
+            emu.run_cycles(10),
+            Err(Chip8Error::StackUnderflow { pc: 0x202 })
+        );
+        assert_eq!(emu.cycle_count(), 1);
+    }
+
+    #[test]
+    fn screen_to_ascii_snapshot() {
+        let mut emu = Emulator::new();
+        emu.v_reg[0] = 0xA;
+        emu.v_reg[2] = 1;
+
+        // LD F, V0; DRW V1, V2, 5
+        emu.run_program(&[0xF029, 0xD125]).unwrap();
+
+        let ascii = emu.screen_to_ascii();
+        let rows: Vec<&str> = ascii.lines().collect();
+        let blank = " ".repeat(64);
+
+        assert_eq!(rows.len(), 32);
+        assert!(rows.iter().all(|row| row.chars().count() == 64));
+        assert_eq!(rows[0], blank);
+        assert_eq!(
+            rows[1..6]
+                .iter()
+                .map(|row| row.trim_end())
+                .collect::<Vec<_>>(),
+            ["████", "█  █", "████", "█  █", "█  █"]
+        );
+        assert!(rows[6..].iter().all(|row| *row == blank));
+        assert!(ascii.ends_with('\n'));
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown opcode 0xe000 at 0x202")]
+    fn panic_policy_names_the_opcode_and_address() {
+        let mut emu = Emulator::new();
+        emu.set_unknown_opcode_policy(UnknownOpcodePolicy::Panic);
+
+        let _ = emu.run_program(&[0x0000, 0xE000]);
+    }
+
+    #[test]
+    fn sprite_rows_wrap_past_the_bottom_edge() {
+        let mut emu = Emulator::new();
+        emu.ram[0x300..0x305].fill(0xFF);
+        emu.v_reg[1] = 30;
+
+        // LD I, 0x300; DRW V0, V1, 5
+        emu.run_program(&[0xA300, 0xD015]).unwrap();
+
+        for (idx, &pixel) in emu.screen.iter().enumerate() {
+            let (x, y) = (idx % SCREEN_WIDTH, idx / SCREEN_WIDTH);
+            let lit = x < 8 && [30, 31, 0, 1, 2].contains(&y);
+            assert_eq!(pixel, lit, "pixel ({x}, {y})");
+        }
+        assert_eq!(emu.v_reg[0xF], 0);
+    }
+
+    #[test]
+    fn nop_policy_counts_each_unknown_opcode() {
+        let mut emu = Emulator::new();
+        emu.set_unknown_opcode_policy(UnknownOpcodePolicy::Nop);
+        // 0xE000; JP 0x200
+        emu.load(&[0xE0, 0x00, 0x12, 0x00]).unwrap();
+
+        let cycles = 20;
+        emu.run_cycles(cycles).unwrap();
+
+        assert_eq!(emu.unknown_opcodes(), [(0xE000, cycles as u64 / 2)]);
+    }
+
+    #[test]
+    fn load_resets_but_load_into_ram_only_writes_its_range() {
+        let mut emu = Emulator::new();
+        // LD V0, 0x42; LD I, 0x345; JP 0x400
+        emu.run_program(&[0x6042, 0xA345, 0x1400]).unwrap();
+        emu.ram[0x500] = 0xAA;
+        let before = emu.state();
+
+        emu.load_into_ram(&[1, 2, 3], 0x600).unwrap();
+        let diffs = emu.diff_state(&before);
+
+        assert_eq!(diffs.len(), 3);
+        assert!(diffs.iter().all(|diff| matches!(
+            diff,
+            StateDiff::Ram {
+                addr: 0x600..=0x602,
+                ..
+            }
+        )));
+        assert_eq!(emu.v_reg[0], 0x42);
+        assert_eq!(emu.i_reg, 0x345);
+        assert_eq!(emu.pc, 0x400);
+        assert_eq!(emu.ram[0x500], 0xAA);
+
+        emu.load(&[1, 2, 3]).unwrap();
+
+        assert_eq!(emu.v_reg[0], 0);
+        assert_eq!(emu.i_reg, 0);
+        assert_eq!(emu.pc, START_ADDR);
+        assert_eq!(emu.ram[0x500], 0);
+        assert_eq!(emu.ram[0x600], 0);
+        assert_eq!(emu.mem_slice(START_ADDR, 3), Some(&[1, 2, 3][..]));
+    }
+
+    #[test]
+    fn reset_clears_hires_and_key_waits() {
+        let quirks = QuirkConfig::super_chip();
+        let mut emu = Emulator::with_seed(7);
+        emu.set_quirks(quirks);
+        // HIGH; LD V0, 9; LD VE, K
+        emu.load(&[0x00, 0xFF, 0x60, 0x09, 0xFE, 0x0A]).unwrap();
+        emu.run_cycles(3).unwrap();
+        assert_eq!(emu.display_size(), (HIRES_WIDTH, HIRES_HEIGHT));
+        assert!(emu.is_waiting_for_key());
+
+        emu.reset();
+
+        let mut fresh = Emulator::with_seed(7);
+        fresh.set_quirks(quirks);
+        assert_eq!(emu.display_size(), (64, 32));
+        assert!(!emu.is_halted());
+        assert!(!emu.is_waiting_for_key());
+        assert_eq!(emu.quirks(), quirks);
+        assert_eq!(emu.state(), fresh.state());
+    }
+
+    #[test]
+    fn exit_halts_until_reset() {
+        let mut emu = Emulator::new();
+        // LD V0, 1; EXIT; LD V0, 2
+        emu.load(&[0x60, 0x01, 0x00, 0xFD, 0x60, 0x02]).unwrap();
+
+        assert_eq!(emu.step(), Ok(Instruction::LoadByte { x: 0, nn: 1 }));
+        assert_eq!(emu.step(), Ok(Instruction::Exit));
+        assert!(emu.is_halted());
+        let halted = emu.state();
+
+        assert_eq!(emu.step(), Err(Chip8Error::Halted { pc: 0x204 }));
+        assert_eq!(emu.state(), halted);
+
+        emu.reset();
+
+        assert!(!emu.is_halted());
+        assert_eq!(emu.step(), Ok(Instruction::Nop));
+    }
+
+    // Scrolls a screen with a full top row, a full left column and one pixel
+    // at (10, 10), then checks every pixel against `lit`
+    fn assert_scroll(op: u16, lit: impl Fn(usize, usize) -> bool) {
+        let mut emu = Emulator::new();
+        for x in 0..SCREEN_WIDTH {
+            emu.screen[x] = true;
+        }
+        for y in 0..SCREEN_HEIGHT {
+            emu.screen[y * SCREEN_WIDTH] = true;
+        }
+        emu.screen[10 + 10 * SCREEN_WIDTH] = true;
+
+        emu.run_program(&[op]).unwrap();
+
+        assert!(emu.take_display_dirty());
+        for (idx, &pixel) in emu.screen.iter().enumerate() {
+            let (x, y) = (idx % SCREEN_WIDTH, idx / SCREEN_WIDTH);
+            assert_eq!(pixel, lit(x, y), "pixel ({x}, {y}) after {op:04X}");
+        }
+    }
+
+    #[test]
+    fn scroll_down_blanks_the_top_rows() {
+        // SCD 3
+        assert_scroll(0x00C3, |x, y| {
+            y == 3 || (x == 0 && y >= 3) || (x, y) == (10, 13)
+        });
+    }
+
+    #[test]
+    fn scroll_right_blanks_the_left_columns() {
+        // SCR
+        assert_scroll(0x00FB, |x, y| {
+            (y == 0 && x >= 4) || x == 4 || (x, y) == (14, 10)
+        });
+    }
+
+    #[test]
+    fn scroll_left_blanks_the_right_columns() {
+        // SCL; the left column scrolls off the screen entirely
+        assert_scroll(0x00FC, |x, y| {
+            (y == 0 && x < SCREEN_WIDTH - 4) || (x, y) == (6, 10)
+        });
+    }
+
+    #[test]
+    fn sound_callback_fires_on_start_and_stop_only() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let mut emu = Emulator::new();
+        let log = Rc::clone(&calls);
+        emu.set_sound_callback(Box::new(move |active| log.borrow_mut().push(active)));
+
+        emu.set_sound_timer(5);
+        assert_eq!(*calls.borrow(), [true]);
+
+        emu.set_sound_timer(3);
+        assert_eq!(*calls.borrow(), [true]);
+
+        for _ in 0..3 {
+            emu.tick_timers();
+        }
+        assert_eq!(emu.sound_timer(), 0);
+        assert_eq!(*calls.borrow(), [true, false]);
+
+        emu.set_sound_timer(0);
+        emu.tick_timers();
+        assert_eq!(*calls.borrow(), [true, false]);
+    }
 }
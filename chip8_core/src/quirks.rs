@@ -0,0 +1,212 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// What `LD [I], VX` and `LD VX, [I]` leave in `I` once they finish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MemoryIncrement {
+    /// `I` is left unchanged (CHIP-48 and SUPER-CHIP 1.1).
+    Unchanged,
+    /// `I += X` (SUPER-CHIP 1.0).
+    ByX,
+    /// `I += X + 1`, leaving `I` just past the last byte (COSMAC VIP).
+    ByXPlusOne,
+}
+
+/// How `DRW` waits for the 60Hz vertical blank, as the COSMAC VIP did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DisplayWait {
+    /// `DRW` runs immediately.
+    Off,
+    /// Every `DRW` stalls until the next vblank, so at most one sprite is
+    /// drawn per frame.
+    PerDraw,
+    /// Only the first `DRW` of a frame stalls; once it has run, the rest of
+    /// that frame's draws go straight through.
+    PerFrame,
+}
+
+/// How `DRW` combines sprite pixels with the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DrawMode {
+    /// Sprite pixels toggle the screen, so drawing twice erases (standard).
+    Xor,
+    /// Sprite pixels only ever turn the screen on, as some extensions offer.
+    Or,
+}
+
+/// Behaviours that differ between CHIP-8 interpreters. The defaults follow
+/// the common modern interpretation of the spec. With the `serde` feature,
+/// fields missing when deserializing take their default values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct QuirkConfig {
+    /// Sprite pixels that wrap around a screen edge still set VF when they
+    /// collide with a lit pixel. On by default, off in `cosmac_vip()`.
+    pub count_wrapped_collisions: bool,
+    /// Sprite pixels past the right or bottom edge wrap around to the other
+    /// side. When off they are clipped, as on the COSMAC VIP; the sprite's
+    /// starting position still wraps either way. On by default, off in
+    /// `cosmac_vip()`.
+    pub sprite_wrapping: bool,
+    /// `SHR VX` and `SHL VX` (8XY6/8XYE) copy VY into VX before shifting,
+    /// as on the COSMAC VIP. When off VX is shifted in place (SUPER-CHIP).
+    /// Off by default, on in `cosmac_vip()`.
+    pub shift_uses_vy: bool,
+    /// `BNNN` is read as `BXNN` and jumps to `XNN + VX` rather than
+    /// `NNN + V0`, as on SUPER-CHIP. Off both by default and in
+    /// `cosmac_vip()`.
+    pub jump_uses_vx: bool,
+    /// `ADD I, VX` (FX1E) sets VF to 1 when `I` passes 0xFFF and to 0
+    /// otherwise, as the Amiga interpreter did. Off both by default and in
+    /// `cosmac_vip()`.
+    pub i_overflow_sets_vf: bool,
+    /// `LD VX, K` (FX0A) only completes once the pressed key is released,
+    /// as on the COSMAC VIP, so one press can't satisfy two waits in a row.
+    /// Off by default, on in `cosmac_vip()`.
+    pub wait_for_key_release: bool,
+    /// Where `I` ends up after `LD [I], VX` and `LD VX, [I]`. `Unchanged` by
+    /// default, `ByXPlusOne` in `cosmac_vip()`.
+    pub memory_increment: MemoryIncrement,
+    /// Whether `DRW` waits for the vertical blank. `Off` by default,
+    /// `PerDraw` in `cosmac_vip()`.
+    pub display_wait: DisplayWait,
+    /// Whether `DRW` XORs or ORs sprites onto the screen. `Xor` both by
+    /// default and in `cosmac_vip()`.
+    pub draw_mode: DrawMode,
+}
+
+impl QuirkConfig {
+    /// The original COSMAC VIP interpreter: sprites clip at the edges, shifts
+    /// read VY, `I` moves past the bytes `LD [I]` touches, `DRW` waits for
+    /// the vertical blank and `LD VX, K` waits for the key to be released.
+    /// `EmulatorBuilder` starts from this.
+    pub fn cosmac_vip() -> Self {
+        Self {
+            count_wrapped_collisions: false,
+            sprite_wrapping: false,
+            shift_uses_vy: true,
+            jump_uses_vx: false,
+            i_overflow_sets_vf: false,
+            wait_for_key_release: true,
+            memory_increment: MemoryIncrement::ByXPlusOne,
+            display_wait: DisplayWait::PerDraw,
+            draw_mode: DrawMode::Xor,
+        }
+    }
+
+    /// SUPER-CHIP 1.1 on the HP 48: sprites clip, shifts work on VX in
+    /// place, `LD [I]` leaves `I` alone and `BNNN` jumps relative to VX.
+    pub fn super_chip() -> Self {
+        Self {
+            count_wrapped_collisions: false,
+            sprite_wrapping: false,
+            shift_uses_vy: false,
+            jump_uses_vx: true,
+            i_overflow_sets_vf: false,
+            wait_for_key_release: false,
+            memory_increment: MemoryIncrement::Unchanged,
+            display_wait: DisplayWait::Off,
+            draw_mode: DrawMode::Xor,
+        }
+    }
+
+    /// XO-CHIP, which went back to the VIP's shifts and `LD [I]` but wraps
+    /// sprites around the screen and never waits for the vertical blank.
+    pub fn xo_chip() -> Self {
+        Self {
+            count_wrapped_collisions: true,
+            sprite_wrapping: true,
+            shift_uses_vy: true,
+            jump_uses_vx: false,
+            i_overflow_sets_vf: false,
+            wait_for_key_release: false,
+            memory_increment: MemoryIncrement::ByXPlusOne,
+            display_wait: DisplayWait::Off,
+            draw_mode: DrawMode::Xor,
+        }
+    }
+}
+
+impl Default for QuirkConfig {
+    fn default() -> Self {
+        Self {
+            count_wrapped_collisions: true,
+            sprite_wrapping: true,
+            shift_uses_vy: false,
+            jump_uses_vx: false,
+            i_overflow_sets_vf: false,
+            wait_for_key_release: false,
+            memory_increment: MemoryIncrement::Unchanged,
+            display_wait: DisplayWait::Off,
+            draw_mode: DrawMode::Xor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Emulator;
+
+    #[test]
+    fn presets_match_the_field_docs() {
+        assert_eq!(
+            QuirkConfig::default(),
+            QuirkConfig {
+                count_wrapped_collisions: true,
+                sprite_wrapping: true,
+                shift_uses_vy: false,
+                jump_uses_vx: false,
+                i_overflow_sets_vf: false,
+                wait_for_key_release: false,
+                memory_increment: MemoryIncrement::Unchanged,
+                display_wait: DisplayWait::Off,
+                draw_mode: DrawMode::Xor,
+            }
+        );
+        assert_eq!(
+            QuirkConfig::cosmac_vip(),
+            QuirkConfig {
+                count_wrapped_collisions: false,
+                sprite_wrapping: false,
+                shift_uses_vy: true,
+                jump_uses_vx: false,
+                i_overflow_sets_vf: false,
+                wait_for_key_release: true,
+                memory_increment: MemoryIncrement::ByXPlusOne,
+                display_wait: DisplayWait::PerDraw,
+                draw_mode: DrawMode::Xor,
+            }
+        );
+    }
+
+    #[test]
+    fn shifts_read_vy_only_with_the_quirk() {
+        // (shift_uses_vy, opcode, expected VX, expected VF) with V0 = 0x81
+        // and V1 = 0x42
+        let cases = [
+            (false, 0x8016, 0x40, 1),
+            (true, 0x8016, 0x21, 0),
+            (false, 0x801E, 0x02, 1),
+            (true, 0x801E, 0x84, 0),
+        ];
+
+        for (shift_uses_vy, op, vx, vf) in cases {
+            let mut emu = Emulator::with_quirks(QuirkConfig {
+                shift_uses_vy,
+                ..QuirkConfig::default()
+            });
+            emu.set_register(0, 0x81);
+            emu.set_register(1, 0x42);
+
+            emu.run_program(&[op]).unwrap();
+
+            assert_eq!(emu.registers()[0], vx, "{op:04X} with {shift_uses_vy}");
+            assert_eq!(emu.registers()[1], 0x42);
+            assert_eq!(emu.registers()[0xF], vf, "{op:04X} with {shift_uses_vy}");
+        }
+    }
+}
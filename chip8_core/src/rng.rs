@@ -0,0 +1,102 @@
+#[cfg(feature = "rand")]
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Marsaglia's xorshift32. Tiny, dependency-free and fully specified, so a
+/// given seed yields the same `RND` results on every platform and build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xorshift32(u32);
+
+impl Xorshift32 {
+    /// The seed goes through one SplitMix64 round first, so that small or
+    /// similar seeds still start from well-mixed states.
+    pub fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        let folded = (z ^ (z >> 32)) as u32;
+
+        // An all-zero state would only ever produce zeros
+        Xorshift32(if folded == 0 { 0x9E37_79B9 } else { folded })
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+
+        self.0 = x;
+        x
+    }
+}
+
+/// Where `RND` gets its numbers from: the OS entropy-backed `rand` generator
+/// by default, or the built-in xorshift when a seed was given (or when the
+/// `rand` feature is disabled).
+pub(crate) enum RngSource {
+    #[cfg(feature = "rand")]
+    Entropy(Box<StdRng>),
+    Seeded(Xorshift32),
+}
+
+impl RngSource {
+    #[cfg(feature = "rand")]
+    pub(crate) fn from_entropy() -> Self {
+        RngSource::Entropy(Box::new(StdRng::from_entropy()))
+    }
+
+    #[cfg(not(feature = "rand"))]
+    pub(crate) fn from_entropy() -> Self {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+
+        RngSource::Seeded(Xorshift32::new(nanos))
+    }
+
+    pub(crate) fn seeded(seed: u64) -> Self {
+        RngSource::Seeded(Xorshift32::new(seed))
+    }
+
+    pub(crate) fn next_u8(&mut self) -> u8 {
+        match self {
+            #[cfg(feature = "rand")]
+            RngSource::Entropy(rng) => rng.gen(),
+            RngSource::Seeded(rng) => (rng.next_u32() >> 24) as u8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Emulator;
+
+    #[test]
+    fn xorshift_sequence() {
+        let mut rng = Xorshift32(1);
+        assert_eq!(rng.next_u32(), 0x0004_2021);
+        assert_eq!(rng.next_u32(), 0x0408_0601);
+        assert_eq!(rng.next_u32(), 0x9DCC_A8C5);
+
+        let mut rng = Xorshift32::new(42);
+        assert_eq!(rng.next_u32(), 0x2CEC_3CA6);
+        assert_eq!(rng.next_u32(), 0xC46D_8A5A);
+    }
+
+    #[test]
+    fn seeded_rnd_uses_xorshift() {
+        let mut emu = Emulator::with_seed(42);
+
+        // RND V0, 0xFF; RND V1, 0xFF; RND V2, 0x0F
+        emu.run_program(&[0xC0FF, 0xC1FF, 0xC20F]).unwrap();
+
+        assert_eq!(emu.registers()[..3], [0x2C, 0xC4, 0x01]);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    HIRES_HEIGHT, HIRES_WIDTH, NUM_KEYS, RAM_SIZE, REGISTER_COUNT, SCREEN_WIDTH, STACK_SIZE,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_big_array::BigArray;
+use std::fmt;
+
+/// A copy of everything a CHIP-8 program can observe: CPU registers, the
+/// stack, timers, RAM and the screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmulatorState {
+    pub pc: u16,
+    pub i_reg: u16,
+    pub v_reg: [u8; REGISTER_COUNT],
+    pub stack_ptr: u16,
+    pub stack: [u16; STACK_SIZE],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub ram: [u8; RAM_SIZE],
+    pub screen: Vec<bool>,
+}
+
+/// Everything needed to resume a session later, including the keys held at
+/// the time. With the `serde` feature it can be written to disk in any
+/// serde format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SaveState {
+    pub pc: u16,
+    #[cfg_attr(feature = "serde", serde(with = "BigArray"))]
+    pub ram: [u8; RAM_SIZE],
+    pub screen: Vec<bool>,
+    pub v_reg: [u8; REGISTER_COUNT],
+    pub i_reg: u16,
+    pub stack_ptr: u16,
+    pub stack: [u16; STACK_SIZE],
+    pub keys: [bool; NUM_KEYS],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
+
+/// Just the CPU side of the machine, for when RAM and the screen don't
+/// matter. `stack` holds only the live entries, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterFile {
+    pub pc: u16,
+    pub i_reg: u16,
+    pub v_reg: [u8; REGISTER_COUNT],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub stack: Vec<u16>,
+}
+
+/// One difference between two states, with our value first and theirs second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateDiff {
+    Pc(u16, u16),
+    Index(u16, u16),
+    Register {
+        x: u8,
+        ours: u8,
+        theirs: u8,
+    },
+    StackPtr(u16, u16),
+    Stack {
+        depth: u8,
+        ours: u16,
+        theirs: u16,
+    },
+    DelayTimer(u8, u8),
+    SoundTimer(u8, u8),
+    Ram {
+        addr: u16,
+        ours: u8,
+        theirs: u8,
+    },
+    Pixel {
+        x: u8,
+        y: u8,
+        ours: bool,
+        theirs: bool,
+    },
+}
+
+impl EmulatorState {
+    /// Lists every difference from `other`, CPU state first, then RAM in
+    /// address order, then the screen row by row.
+    pub fn diff(&self, other: &EmulatorState) -> Vec<StateDiff> {
+        let mut diffs = Vec::new();
+
+        if self.pc != other.pc {
+            diffs.push(StateDiff::Pc(self.pc, other.pc));
+        }
+
+        if self.i_reg != other.i_reg {
+            diffs.push(StateDiff::Index(self.i_reg, other.i_reg));
+        }
+
+        for (x, (&ours, &theirs)) in self.v_reg.iter().zip(&other.v_reg).enumerate() {
+            if ours != theirs {
+                diffs.push(StateDiff::Register {
+                    x: x as u8,
+                    ours,
+                    theirs,
+                });
+            }
+        }
+
+        if self.stack_ptr != other.stack_ptr {
+            diffs.push(StateDiff::StackPtr(self.stack_ptr, other.stack_ptr));
+        }
+
+        for (depth, (&ours, &theirs)) in self.stack.iter().zip(&other.stack).enumerate() {
+            if ours != theirs {
+                diffs.push(StateDiff::Stack {
+                    depth: depth as u8,
+                    ours,
+                    theirs,
+                });
+            }
+        }
+
+        if self.delay_timer != other.delay_timer {
+            diffs.push(StateDiff::DelayTimer(self.delay_timer, other.delay_timer));
+        }
+
+        if self.sound_timer != other.sound_timer {
+            diffs.push(StateDiff::SoundTimer(self.sound_timer, other.sound_timer));
+        }
+
+        for (addr, (&ours, &theirs)) in self.ram.iter().zip(&other.ram).enumerate() {
+            if ours != theirs {
+                diffs.push(StateDiff::Ram {
+                    addr: addr as u16,
+                    ours,
+                    theirs,
+                });
+            }
+        }
+
+        // The buffer's size says which resolution the screen was in
+        let width = if self.screen.len() == HIRES_WIDTH * HIRES_HEIGHT {
+            HIRES_WIDTH
+        } else {
+            SCREEN_WIDTH
+        };
+
+        for (i, (&ours, &theirs)) in self.screen.iter().zip(&other.screen).enumerate() {
+            if ours != theirs {
+                diffs.push(StateDiff::Pixel {
+                    x: (i % width) as u8,
+                    y: (i / width) as u8,
+                    ours,
+                    theirs,
+                });
+            }
+        }
+
+        diffs
+    }
+}
+
+impl fmt::Display for RegisterFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PC 0x{:03X}  I 0x{:03X}  DT {:02X}  ST {:02X}",
+            self.pc, self.i_reg, self.delay_timer, self.sound_timer
+        )?;
+
+        for (x, v) in self.v_reg.iter().enumerate() {
+            let sep = if x % 8 == 0 { "\n" } else { "  " };
+            write!(f, "{}V{:X} {:02X}", sep, x, v)?;
+        }
+
+        let stack: Vec<String> = self.stack.iter().map(|a| format!("0x{:03X}", a)).collect();
+        write!(f, "\nstack [{}]", stack.join(", "))
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateDiff::Pc(ours, theirs) => write!(f, "PC: 0x{:03X} != 0x{:03X}", ours, theirs),
+            StateDiff::Index(ours, theirs) => write!(f, "I: 0x{:03X} != 0x{:03X}", ours, theirs),
+            StateDiff::Register { x, ours, theirs } => {
+                write!(f, "V{:X}: 0x{:02X} != 0x{:02X}", x, ours, theirs)
+            }
+            StateDiff::StackPtr(ours, theirs) => write!(f, "SP: {} != {}", ours, theirs),
+            StateDiff::Stack {
+                depth,
+                ours,
+                theirs,
+            } => write!(f, "stack[{}]: 0x{:03X} != 0x{:03X}", depth, ours, theirs),
+            StateDiff::DelayTimer(ours, theirs) => write!(f, "DT: {} != {}", ours, theirs),
+            StateDiff::SoundTimer(ours, theirs) => write!(f, "ST: {} != {}", ours, theirs),
+            StateDiff::Ram { addr, ours, theirs } => {
+                write!(f, "RAM 0x{:03X}: 0x{:02X} != 0x{:02X}", addr, ours, theirs)
+            }
+            StateDiff::Pixel { x, y, ours, theirs } => {
+                write!(f, "pixel ({}, {}): {} != {}", x, y, ours, theirs)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StateDiff;
+    use crate::Emulator;
+
+    #[test]
+    fn register_snapshot_after_arithmetic() {
+        let mut emu = Emulator::new();
+
+        // LD V0, 0xF0; LD V1, 0x20; ADD V0, V1; LD V2, 5; SUB V2, V1;
+        // LD I, 0x2A0; CALL 0x300
+        emu.run_program(&[0x60F0, 0x6120, 0x8014, 0x6205, 0x8215, 0xA2A0, 0x2300])
+            .unwrap();
+        let regs = emu.register_snapshot();
+
+        assert_eq!(regs.v_reg[..3], [0x10, 0x20, 0xE5]);
+        assert_eq!(regs.v_reg[0xF], 0);
+        assert_eq!(regs.i_reg, 0x2A0);
+        assert_eq!(regs.pc, 0x300);
+        assert_eq!(regs.stack, [0x20E]);
+        assert_eq!(
+            regs.to_string(),
+            "PC 0x300  I 0x2A0  DT 00  ST 00\n\
+             V0 10  V1 20  V2 E5  V3 00  V4 00  V5 00  V6 00  V7 00\n\
+             V8 00  V9 00  VA 00  VB 00  VC 00  VD 00  VE 00  VF 00\n\
+             stack [0x20E]"
+        );
+    }
+
+    #[test]
+    fn diff_reports_a_changed_register() {
+        let mut emu = Emulator::new();
+        emu.set_register(3, 0x05);
+        let before = emu.state();
+
+        emu.set_register(3, 0x07);
+        let diffs = before.diff(&emu.state());
+
+        assert_eq!(
+            diffs,
+            [StateDiff::Register {
+                x: 3,
+                ours: 0x05,
+                theirs: 0x07
+            }]
+        );
+        assert_eq!(diffs[0].to_string(), "V3: 0x05 != 0x07");
+        assert_eq!(emu.diff_state(&before).len(), 1);
+    }
+}
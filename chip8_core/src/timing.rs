@@ -0,0 +1,82 @@
+//! Rough model of how long each instruction took on the COSMAC VIP's
+//! interpreter, in 1802 machine cycles (8 clocks at 1.76MHz).
+//!
+//! The shape follows Laurence Scotford's walkthrough of the VIP interpreter
+//! ("Chip-8 on the COSMAC VIP"): every instruction pays for fetch and decode,
+//! `CLS` and `LD B, VX` loop over memory, and `DRW` pays per sprite row, with
+//! rows costing about twice as much when X isn't a multiple of 8 because each
+//! byte has to be shifted across two screen bytes. The numbers themselves are
+//! rounded averages rather than exact counts, and the time the display
+//! interrupt steals from each frame is folded into the budget.
+
+use crate::Instruction;
+
+/// Machine cycles the VIP had for the interpreter in one 60Hz frame.
+pub const VIP_CYCLES_PER_FRAME: u64 = 3668;
+
+const FETCH_DECODE: u64 = 340;
+const CLEAR_SCREEN: u64 = 1_600;
+const BCD: u64 = 360;
+const DRAW_SETUP: u64 = 180;
+const DRAW_ROW_ALIGNED: u64 = 200;
+const DRAW_ROW_SHIFTED: u64 = 400;
+
+/// Cost of executing `instruction`, where `x_coord` is the value of VX at the
+/// time (only used by `DRW`).
+pub(crate) fn vip_cost(instruction: Instruction, x_coord: u8) -> u64 {
+    let extra = match instruction {
+        Instruction::Cls => CLEAR_SCREEN,
+        Instruction::Draw { n, .. } => {
+            let rows = n as u64;
+            let row_cost = if x_coord & 7 == 0 {
+                DRAW_ROW_ALIGNED
+            } else {
+                DRAW_ROW_SHIFTED
+            };
+
+            DRAW_SETUP + rows * row_cost
+        }
+        Instruction::Bcd { .. } => BCD,
+        _ => 0,
+    };
+
+    FETCH_DECODE + extra
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Emulator;
+
+    fn frame_cost(rom: &[u8]) -> u64 {
+        let mut emu = Emulator::new();
+        emu.load(rom).unwrap();
+        emu.run_cycles(10).unwrap();
+        emu.vip_cycles()
+    }
+
+    #[test]
+    fn draws_cost_more_than_arithmetic() {
+        // DRW V0, V1, 15; JP 0x200
+        let draws = frame_cost(&[0xD0, 0x1F, 0x12, 0x00]);
+        // ADD V0, 1; JP 0x200
+        let arithmetic = frame_cost(&[0x70, 0x01, 0x12, 0x00]);
+
+        assert_eq!(arithmetic, 10 * FETCH_DECODE);
+        assert!(draws > arithmetic);
+    }
+
+    #[test]
+    fn unaligned_draws_cost_more() {
+        let draw = Instruction::Draw { x: 0, y: 1, n: 5 };
+
+        assert_eq!(
+            vip_cost(draw, 8),
+            FETCH_DECODE + DRAW_SETUP + 5 * DRAW_ROW_ALIGNED
+        );
+        assert_eq!(
+            vip_cost(draw, 9),
+            FETCH_DECODE + DRAW_SETUP + 5 * DRAW_ROW_SHIFTED
+        );
+    }
+}
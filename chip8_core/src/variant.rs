@@ -0,0 +1,91 @@
+use crate::{control_flow_graph, Instruction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Variant {
+    Classic,
+    SuperChip,
+    XoChip,
+}
+
+/// Guesses which CHIP-8 dialect a ROM targets by looking for opcodes that
+/// only exist in an extension. Only code reachable from `0x200` is checked,
+/// so sprite data that happens to look like an extension opcode doesn't
+/// count. XO-CHIP wins over SUPER-CHIP since XO-CHIP is a superset of it.
+pub fn detect_variant(data: &[u8]) -> Chip8Variant {
+    let mut variant = Chip8Variant::Classic;
+
+    for block in control_flow_graph(data).blocks {
+        for op in block.ops {
+            match Instruction::decode(op) {
+                Instruction::ScrollDown { .. }
+                | Instruction::ScrollRight
+                | Instruction::ScrollLeft
+                | Instruction::Exit
+                | Instruction::LowRes
+                | Instruction::HighRes
+                | Instruction::Draw { n: 0, .. } => variant = Chip8Variant::SuperChip,
+                // Extension opcodes this interpreter doesn't run yet
+                Instruction::Unknown(op) => match (op >> 12, (op >> 8) & 0xF, op & 0xFF) {
+                    (0xF, 0, 0x00) | (0xF, _, 0x01) | (0xF, 0, 0x02) | (0xF, _, 0x3A) => {
+                        return Chip8Variant::XoChip
+                    }
+                    (0xF, _, 0x30) | (0xF, _, 0x75) => variant = Chip8Variant::SuperChip,
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+    }
+
+    variant
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(ops: &[u16]) -> Vec<u8> {
+        ops.iter().flat_map(|op| op.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn sprite_data_that_looks_like_schip_is_classic() {
+        // CLS; LD I, 0x20A; DRW V0, V1, 5; JP 0x206, then the sprite bytes
+        // 00 FF 00 FC D0 10, which read as HIGH, SCL and DRW V0, V1, 0
+        let data = rom(&[0x00E0, 0xA20A, 0xD015, 0x1206, 0x00FF, 0x00FC, 0xD010]);
+
+        assert_eq!(detect_variant(&data), Chip8Variant::Classic);
+    }
+
+    #[test]
+    fn reachable_schip_opcodes() {
+        for op in [0x00FF, 0x00C4, 0x00FD, 0xD120, 0xF330, 0xF575] {
+            assert_eq!(
+                detect_variant(&rom(&[op, 0x1202])),
+                Chip8Variant::SuperChip,
+                "{:04X}",
+                op
+            );
+        }
+    }
+
+    #[test]
+    fn reachable_xo_chip_opcodes() {
+        for op in [0xF000, 0xF201, 0xF002, 0xF13A] {
+            assert_eq!(
+                detect_variant(&rom(&[0x00FF, op, 0x1204])),
+                Chip8Variant::XoChip,
+                "{:04X}",
+                op
+            );
+        }
+    }
+
+    #[test]
+    fn code_after_a_jump_is_skipped() {
+        // JP 0x204; HIGH (never runs); LD V0, 1; JP 0x206
+        let data = rom(&[0x1204, 0x00FF, 0x6001, 0x1206]);
+
+        assert_eq!(detect_variant(&data), Chip8Variant::Classic);
+    }
+}
@@ -0,0 +1,143 @@
+const FRAME_HZ: u32 = 60;
+// Ramp at each end of a tone so it doesn't click, in seconds
+const FADE_SECS: f32 = 0.002;
+
+/// Square-wave generator that is fed one slice of a 60Hz frame at a time.
+/// Asking for tone for every slice the sound timer is running makes a beep
+/// last precisely `sound_timer` 60ths of a second, even when the timer is
+/// re-set mid-tone, and a timer of 1 still produces an audible click.
+pub struct Beeper {
+    sample_rate: u32,
+    slices_per_second: u32,
+    // Leftover sample fraction so rates like 22050Hz don't drift
+    carry: u32,
+    phase: f32,
+    frequency: f32,
+    amplitude: f32,
+    // Samples into the current tone, for the fade-in; None between tones
+    position: Option<usize>,
+}
+
+impl Beeper {
+    /// `volume` runs from 0.0 (silent) to 1.0 (full scale).
+    pub fn new(sample_rate: u32, slices_per_frame: u32, frequency: f32, volume: f32) -> Self {
+        Self {
+            sample_rate,
+            slices_per_second: FRAME_HZ * slices_per_frame,
+            carry: 0,
+            phase: 0.0,
+            frequency,
+            amplitude: volume.clamp(0.0, 1.0) * i16::MAX as f32,
+            position: None,
+        }
+    }
+
+    /// One slice of tone, continuing the wave from the previous slice.
+    pub fn slice(&mut self) -> Vec<i16> {
+        let total = self.sample_rate + self.carry;
+        let len = (total / self.slices_per_second) as usize;
+        self.carry = total % self.slices_per_second;
+
+        let fade_len = self.fade_len();
+        let start = self.position.unwrap_or(0);
+
+        self.position = Some(start + len);
+
+        (start..start + len)
+            .map(|i| self.next_sample(i.min(fade_len) as f32 / fade_len as f32))
+            .collect()
+    }
+
+    /// Ends the current tone, returning a short fade-out to queue after the
+    /// last slice (empty if no tone was playing). The next tone starts at the
+    /// top of the wave.
+    pub fn stop(&mut self) -> Vec<i16> {
+        if self.position.take().is_none() {
+            return Vec::new();
+        }
+
+        let fade_len = self.fade_len();
+        let tail = (0..fade_len)
+            .map(|i| self.next_sample(1.0 - i as f32 / fade_len as f32))
+            .collect();
+
+        self.phase = 0.0;
+        tail
+    }
+
+    fn fade_len(&self) -> usize {
+        ((self.sample_rate as f32 * FADE_SECS) as usize).max(1)
+    }
+
+    // Next sample of the square wave, scaled by `gain`
+    fn next_sample(&mut self, gain: f32) -> i16 {
+        let level = if self.phase < 0.5 {
+            self.amplitude
+        } else {
+            -self.amplitude
+        };
+
+        self.phase = (self.phase + self.frequency / self.sample_rate as f32).fract();
+        (level * gain) as i16
+    }
+}
+
+/// Parses a beep frequency in Hz, which must be positive.
+pub fn parse_frequency(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(hz) if hz > 0.0 && hz.is_finite() => Ok(hz),
+        _ => Err(format!("expected a positive frequency in Hz, got '{}'", s)),
+    }
+}
+
+/// Parses a volume, clamping it to 0.0-1.0.
+pub fn parse_volume(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(volume) if !volume.is_nan() => Ok(volume.clamp(0.0, 1.0)),
+        _ => Err(format!("expected a volume from 0.0 to 1.0, got '{}'", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::Emulator;
+
+    #[test]
+    fn re_set_timer_extends_the_tone_exactly() {
+        let mut emu = Emulator::new();
+        let mut beeper = Beeper::new(44_100, 2, 440.0, 1.0);
+        let mut samples = 0;
+
+        // LD V0, 10; LD ST, V0
+        emu.run_program(&[0x600A, 0xF018]).unwrap();
+
+        for frame in 0..40 {
+            // Halfway through, LD V0, 20; LD ST, V0
+            if frame == 5 {
+                emu.run_program(&[0x6014, 0xF018]).unwrap();
+            }
+
+            for _ in 0..2 {
+                if emu.is_sound_active() {
+                    samples += beeper.slice().len();
+                }
+            }
+
+            emu.tick_timers();
+        }
+
+        // 5 frames of the first beep, then 20 of the second, at 735 per frame
+        assert_eq!(samples, 25 * 735);
+        assert_eq!(beeper.stop().len(), beeper.fade_len());
+    }
+
+    #[test]
+    fn one_frame_beep_is_still_a_click() {
+        let mut beeper = Beeper::new(48_000, 1, 440.0, 1.0);
+
+        assert_eq!(beeper.slice().len(), 800);
+        assert!(!beeper.stop().is_empty());
+        assert!(beeper.stop().is_empty());
+    }
+}
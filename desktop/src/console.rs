@@ -0,0 +1,31 @@
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Reads debugger commands from stdin on a background thread so the game
+/// loop never blocks waiting for input.
+pub struct Console {
+    lines: Receiver<String>,
+}
+
+impl Console {
+    pub fn spawn() -> Self {
+        let (sender, lines) = mpsc::channel();
+
+        thread::spawn(move || {
+            // Stops at EOF, on a read error, or once the game loop has exited
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { lines }
+    }
+
+    /// Commands typed since the last call, without waiting for more.
+    pub fn pending(&self) -> Vec<String> {
+        self.lines.try_iter().collect()
+    }
+}
@@ -0,0 +1,156 @@
+use crate::keymap::KEYPAD_LAYOUT;
+use crate::palette::{parse_hex_color, Palette};
+use chip8_core::{Emulator, QuirkConfig};
+
+const DISASM_LINES: u16 = 10;
+
+type QuirkFlag = fn(&mut QuirkConfig) -> &mut bool;
+
+/// Quirks that can be flipped at runtime, in hotkey order (Ctrl+1, Ctrl+2...)
+const QUIRKS: [(&str, QuirkFlag); 6] = [
+    ("count_wrapped_collisions", |q| {
+        &mut q.count_wrapped_collisions
+    }),
+    ("sprite_wrapping", |q| &mut q.sprite_wrapping),
+    ("shift_uses_vy", |q| &mut q.shift_uses_vy),
+    ("jump_uses_vx", |q| &mut q.jump_uses_vx),
+    ("i_overflow_sets_vf", |q| &mut q.i_overflow_sets_vf),
+    ("wait_for_key_release", |q| &mut q.wait_for_key_release),
+];
+
+/// Flips the quirk bound to Ctrl+`n` (1-based) on the live emulator.
+pub fn toggle_quirk(emu: &mut Emulator, n: usize) {
+    if let Some((name, flag)) = n.checked_sub(1).and_then(|i| QUIRKS.get(i)) {
+        let mut quirks = emu.quirks();
+        let value = flag(&mut quirks);
+
+        *value = !*value;
+        println!("{} = {}", name, *value);
+        emu.set_quirks(quirks);
+    }
+}
+
+/// Runs one command typed into the console while paused.
+pub fn run_command(emu: &mut Emulator, palette: &mut Palette, line: &str) {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    match words.as_slice() {
+        [] => (),
+        ["poke", addr, byte] => poke(emu, addr, byte),
+        ["color", planes, hex] => set_color(palette, planes, hex),
+        _ => println!(
+            "Unknown command '{}' (try: poke <addr> <byte>, color <0-3> <RRGGBB>)",
+            line.trim()
+        ),
+    }
+}
+
+// Recolours one plane combination of the active palette
+fn set_color(palette: &mut Palette, planes: &str, hex: &str) {
+    let planes = match planes.parse::<usize>() {
+        Ok(planes) if planes < palette.colors.len() => planes,
+        _ => {
+            println!("Plane combination must be 0-3");
+            return;
+        }
+    };
+
+    match parse_hex_color(hex) {
+        Ok(color) => palette.colors[planes] = color,
+        Err(err) => println!("{}", err),
+    }
+}
+
+// Writes one byte and shows the instruction it now belongs to
+fn poke(emu: &mut Emulator, addr: &str, byte: &str) {
+    let addr = u16::from_str_radix(addr.trim_start_matches("0x"), 16);
+    let byte = u8::from_str_radix(byte.trim_start_matches("0x"), 16);
+
+    let (addr, byte) = match (addr, byte) {
+        (Ok(addr), Ok(byte)) => (addr, byte),
+        _ => {
+            println!("Usage: poke <addr> <byte>, both in hex");
+            return;
+        }
+    };
+
+    if let Err(err) = emu.write_byte(addr, byte) {
+        println!("{}", err);
+        return;
+    }
+
+    let op_addr = addr & !1;
+
+    if let Some(mnemonic) = emu.decode_at(op_addr) {
+        println!("  0x{:03X}  {}", op_addr, mnemonic);
+    }
+}
+
+pub fn print_quirks(emu: &Emulator) {
+    let mut quirks = emu.quirks();
+
+    for (i, (name, flag)) in QUIRKS.iter().enumerate() {
+        println!("[Ctrl+{}] {} = {}", i + 1, name, *flag(&mut quirks));
+    }
+}
+
+// Held keys in the keypad's 4x4 layout, with `.` for released ones
+fn print_keypad(emu: &Emulator) {
+    let keys = emu.keys();
+
+    for row in KEYPAD_LAYOUT.chunks(4) {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|&key| {
+                if keys[key] {
+                    format!("{:X}", key)
+                } else {
+                    ".".to_string()
+                }
+            })
+            .collect();
+
+        println!("{}", cells.join(" "));
+    }
+}
+
+pub fn print_state(emu: &Emulator) {
+    let pc = emu.program_counter();
+
+    println!(
+        "PC 0x{:03X}  I 0x{:03X}  DT {:02X}  ST {:02X}",
+        pc,
+        emu.index(),
+        emu.delay_timer(),
+        emu.sound_timer()
+    );
+
+    let regs: Vec<String> = emu
+        .registers()
+        .iter()
+        .enumerate()
+        .map(|(i, v)| format!("V{:X} {:02X}", i, v))
+        .collect();
+
+    println!("{}", regs[..8].join("  "));
+    println!("{}", regs[8..].join("  "));
+    println!();
+    print_keypad(emu);
+    println!();
+    print_quirks(emu);
+    println!();
+
+    for line in 0..DISASM_LINES {
+        let addr = pc.wrapping_add(line * 2);
+
+        match emu.decode_at(addr) {
+            Some(mnemonic) => {
+                let marker = if addr == pc { ">" } else { " " };
+                println!("{} 0x{:03X}  {}", marker, addr, mnemonic);
+            }
+            None => break,
+        }
+    }
+
+    println!();
+}
@@ -0,0 +1,82 @@
+use chip8_core::disasm::{disassemble, is_known};
+use chip8_core::{Chip8Error, Emulator};
+
+/// Spots the usual reasons a ROM shows nothing but a black screen or dies
+/// straight away, each with a hint at what to do about it.
+pub fn rom_warnings(data: &[u8]) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if data.is_empty() {
+        warnings.push("ROM file is empty; check the path and that the download finished".into());
+        return warnings;
+    }
+
+    if data.iter().all(|&byte| byte == 0) {
+        warnings.push("ROM is all zeros, so there is no program to run".into());
+    }
+
+    // Ask the core, so this always agrees with what `load` accepts
+    if let Err(Chip8Error::RomTooLarge { size, max }) = Emulator::new().load(data) {
+        warnings.push(format!(
+            "ROM is {} bytes but CHIP-8 programs are at most {}; it may be an archive or another console's ROM",
+            size, max
+        ));
+    }
+
+    if let [hi, lo, ..] = *data {
+        let op = ((hi as u16) << 8) | lo as u16;
+
+        if !is_known(op) {
+            warnings.push(format!(
+                "First instruction {} isn't valid CHIP-8; the file may not be a CHIP-8 ROM",
+                disassemble(op)
+            ));
+        }
+    } else {
+        warnings.push("ROM is a single byte, too short to hold an instruction".into());
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_rom() {
+        let warnings = rom_warnings(&[]);
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("empty"));
+    }
+
+    #[test]
+    fn all_zero_rom() {
+        let warnings = rom_warnings(&[0; 64]);
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("all zeros"));
+    }
+
+    #[test]
+    fn oversized_rom() {
+        let mut rom = vec![0x12, 0x00];
+        rom.resize(3585, 0xAA);
+        let warnings = rom_warnings(&rom);
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("3585 bytes"));
+        assert!(warnings[0].contains("at most 3584"));
+        assert!(rom_warnings(&rom[..3584]).is_empty());
+    }
+
+    #[test]
+    fn invalid_first_opcode() {
+        let warnings = rom_warnings(&[0xE0, 0x00, 0x12, 0x00]);
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("First instruction"));
+        assert!(rom_warnings(&[0x00, 0xE0, 0x12, 0x00]).is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use sdl2::keyboard::Keycode;
+use std::fs;
+
+/// Host key for each CHIP-8 key, laid out like the keypad:
+///
+/// ```text
+/// 1 2 3 4        1 2 3 C
+/// Q W E R   ->   4 5 6 D
+/// A S D F        7 8 9 E
+/// Z X C V        A 0 B F
+/// ```
+pub const KEYMAP: [(Keycode, usize); 16] = [
+    (Keycode::Num1, 0x1),
+    (Keycode::Num2, 0x2),
+    (Keycode::Num3, 0x3),
+    (Keycode::Num4, 0xC),
+    (Keycode::Q, 0x4),
+    (Keycode::W, 0x5),
+    (Keycode::E, 0x6),
+    (Keycode::R, 0xD),
+    (Keycode::A, 0x7),
+    (Keycode::S, 0x8),
+    (Keycode::D, 0x9),
+    (Keycode::F, 0xE),
+    (Keycode::Z, 0xA),
+    (Keycode::X, 0x0),
+    (Keycode::C, 0xB),
+    (Keycode::V, 0xF),
+];
+
+/// CHIP-8 keys in the order they sit on the keypad, row by row.
+pub const KEYPAD_LAYOUT: [usize; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+/// Which host keys drive which CHIP-8 keys. Defaults to `KEYMAP`; several
+/// host keys may share a CHIP-8 key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    keys: Vec<(Keycode, usize)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            keys: KEYMAP.to_vec(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Reads a keymap file of `KEY = HEX` lines, where `KEY` is an SDL key
+    /// name and `HEX` a CHIP-8 key from `0x0` to `0xF`:
+    ///
+    /// ```toml
+    /// # AZERTY
+    /// A = 0x4
+    /// Z = 0x5
+    /// ```
+    pub fn load(path: &str) -> Result<Keymap, String> {
+        let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut keys = Vec::new();
+
+        // One entry per line, so every error can name the line it came from
+        for (num, line) in contents.lines().enumerate() {
+            let err = |msg: String| format!("line {}: {}", num + 1, msg);
+            // toml counts lines within the snippet, which is always line 1
+            let entry: toml::value::Table = toml::from_str(line).map_err(|e| {
+                let msg = e.to_string();
+                err(msg.split(" at line ").next().unwrap_or(&msg).to_string())
+            })?;
+
+            for (name, value) in entry {
+                let host = Keycode::from_name(&name)
+                    .ok_or_else(|| err(format!("unknown key '{}'", name)))?;
+                let chip8 = value
+                    .as_integer()
+                    .filter(|k| (0..16).contains(k))
+                    .ok_or_else(|| err(format!("'{}' must map to 0x0-0xF", name)))?;
+
+                keys.push((host, chip8 as usize));
+            }
+        }
+
+        Ok(Keymap { keys })
+    }
+
+    pub fn get(&self, key: Keycode) -> Option<usize> {
+        self.keys
+            .iter()
+            .find(|(host, _)| *host == key)
+            .map(|(_, chip8)| *chip8)
+    }
+
+    pub fn entries(&self) -> &[(Keycode, usize)] {
+        &self.keys
+    }
+}
@@ -0,0 +1,108 @@
+use crate::keymap::{Keymap, KEYPAD_LAYOUT};
+use crate::palette::Palette;
+use sdl2::event::Event;
+use sdl2::keyboard::{KeyboardState, Keycode, Scancode};
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+/// One host key from the keymap, the CHIP-8 key it drives and whether it is
+/// currently held.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct KeyStatus {
+    pub host: Keycode,
+    pub chip8: usize,
+    pub pressed: bool,
+}
+
+/// Mapping and pressed state of every host key in the keymap, in keypad
+/// order. Useful for working out why a key seems to do nothing.
+pub fn key_report(keymap: &Keymap, state: &KeyboardState) -> Vec<KeyStatus> {
+    keymap
+        .entries()
+        .iter()
+        .map(|&(host, chip8)| KeyStatus {
+            host,
+            chip8,
+            pressed: Scancode::from_keycode(host)
+                .is_some_and(|scancode| state.is_scancode_pressed(scancode)),
+        })
+        .collect()
+}
+
+/// Shows the keypad instead of running the ROM: each CHIP-8 key lights up
+/// while its host key is held, and the console lists the held keys whenever
+/// they change. Returns when Escape is pressed or the window is closed.
+pub fn run(
+    canvas: &mut Canvas<Window>,
+    event_pump: &mut EventPump,
+    keymap: &Keymap,
+    palette: &Palette,
+) {
+    println!("Key test: press keys to check the mapping, Escape to quit");
+
+    for &(host, chip8) in keymap.entries() {
+        println!("  {:<4} -> {:X}", host.name(), chip8);
+    }
+
+    let mut last_report = Vec::new();
+
+    loop {
+        for evt in event_pump.poll_iter() {
+            match evt {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return,
+                _ => (),
+            }
+        }
+
+        let report = key_report(keymap, &event_pump.keyboard_state());
+
+        if report != last_report {
+            let held: Vec<String> = report
+                .iter()
+                .filter(|key| key.pressed)
+                .map(|key| format!("{} -> {:X}", key.host.name(), key.chip8))
+                .collect();
+
+            if held.is_empty() {
+                println!("Held: none");
+            } else {
+                println!("Held: {}", held.join(", "));
+            }
+
+            last_report = report;
+        }
+
+        draw_keypad(&last_report, palette, canvas);
+        canvas.present();
+    }
+}
+
+// 4x4 grid in keypad layout, lit where any host key for that CHIP-8 key is held
+fn draw_keypad(report: &[KeyStatus], palette: &Palette, canvas: &mut Canvas<Window>) {
+    let (width, height) = canvas.output_size().unwrap();
+    let (cell_w, cell_h) = (width / 4, height / 4);
+
+    canvas.set_draw_color(palette.bg());
+    canvas.clear();
+
+    for (i, &chip8) in KEYPAD_LAYOUT.iter().enumerate() {
+        let pressed = report.iter().any(|key| key.chip8 == chip8 && key.pressed);
+        let x = (i % 4) as u32 * cell_w;
+        let y = (i / 4) as u32 * cell_h;
+        let rect = Rect::new(x as i32 + 2, y as i32 + 2, cell_w - 4, cell_h - 4);
+
+        canvas.set_draw_color(palette.fg());
+
+        if pressed {
+            canvas.fill_rect(rect).unwrap();
+        } else {
+            canvas.draw_rect(rect).unwrap();
+        }
+    }
+}
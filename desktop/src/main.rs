@@ -1,84 +1,449 @@
-use chip8_core::{Emulator, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::{
+    detect_variant, disasm, rom_hash, Chip8Variant, Demo, Emulator, QuirkConfig,
+    UnknownOpcodePolicy, HIRES_HEIGHT, HIRES_WIDTH, RAM_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH,
+    VIP_CYCLES_PER_FRAME,
+};
 use clap::Parser;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-use std::fs::File;
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+use sdl2::EventPump;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
 use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-const BLACK: Color = Color::RGB(0, 0, 0);
-const WHITE: Color = Color::RGB(255, 255, 255);
-const TICKS_PER_FRAME: usize = 10;
+mod audio;
+mod console;
+mod debugger;
+mod diagnose;
+mod keymap;
+mod keytest;
+mod memview;
+mod palette;
+mod patch;
+mod savestate;
+mod settings;
+mod stats;
+
+use audio::{parse_frequency, parse_volume, Beeper};
+use console::Console;
+use keymap::Keymap;
+use palette::{format_hex_color, parse_hex_color, parse_palette_name, Palette, PRESETS};
+use settings::RomSettings;
+use stats::FrameStats;
+
+const DEFAULT_SCALE: u32 = 15;
+const SAMPLE_RATE: i32 = 44_100;
+
+const HOTKEYS: &str = "\
+HOTKEYS:
+    Escape    Quit
+    Space     Pause / resume
+    N         Step one instruction while paused
+    [ / ]     Previous / next colour palette
+    Ctrl+1-9  Toggle a quirk (with --debug)
+    F3        Show / hide the RAM heatmap
+    F5        Quicksave to <rom>.state
+    F9        Quickload from <rom>.state
+    F12       Dump RAM to ram-<timestamp>.bin";
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about)]
+#[clap(author, version, about, after_help = HOTKEYS)]
 struct Args {
     /// Path to ROM file
     #[clap(value_parser)]
     path: String,
 
-    /// Window scale amount
-    #[clap(short, long, value_parser, default_value_t = 15)]
-    scale: u32,
+    /// Window scale amount [default: 15]
+    #[clap(short, long, value_parser)]
+    scale: Option<u32>,
+
+    /// Instructions to run per 60Hz frame; raise it if a game runs too slowly
+    #[clap(short, long, value_parser, default_value_t = 10)]
+    ticks_per_frame: usize,
+
+    /// Queue each frame's beep audio in this many slices so beeps start and stop closer to the instruction that caused them (the timers still count down once per frame)
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..=60), default_value_t = 1)]
+    audio_slices: u32,
+
+    /// Colour palette to start with [default: classic]
+    #[clap(long, value_parser = parse_palette_name)]
+    palette: Option<String>,
+
+    /// Foreground colour as RRGGBB hex, overriding the starting palette's
+    #[clap(long, value_parser = parse_hex_color)]
+    fg: Option<Color>,
+
+    /// Background colour as RRGGBB hex, overriding the starting palette's
+    #[clap(long, value_parser = parse_hex_color)]
+    bg: Option<Color>,
+
+    /// Remember the scale, palette, colours and quirks for this ROM when quitting
+    #[clap(long, value_parser)]
+    save_settings: bool,
+
+    /// Pause emulation as soon as the sound timer starts a beep (Space resumes)
+    #[clap(long, value_parser)]
+    break_on_beep: bool,
+
+    /// Re-read the keyboard before every instruction instead of once per frame
+    #[clap(long, value_parser)]
+    low_latency_input: bool,
+
+    /// Show frames and instructions per second in the window title
+    #[clap(long, value_parser)]
+    show_stats: bool,
+
+    /// Enable debug hotkeys such as toggling quirks at runtime
+    #[clap(long, value_parser)]
+    debug: bool,
+
+    /// Tint the screen while the sound timer is running, as a visual cue for the beep
+    #[clap(long, value_parser)]
+    visual_beep: bool,
+
+    /// Colour of the --visual-beep tint, as RRGGBB hex
+    #[clap(long, value_parser = parse_hex_color, default_value = "FF0000")]
+    visual_beep_color: Color,
+
+    /// Opacity of the --visual-beep tint, from 0 (invisible) to 255 (solid)
+    #[clap(long, value_parser, default_value_t = 96)]
+    visual_beep_alpha: u8,
+
+    /// Pitch of the beep in Hz
+    #[clap(long, value_parser = parse_frequency, default_value_t = 440.0)]
+    beep_hz: f32,
+
+    /// Beep volume from 0.0 to 1.0; values outside the range are clamped
+    #[clap(long, value_parser = parse_volume, default_value_t = 0.25)]
+    volume: f32,
+
+    /// TOML file mapping key names to CHIP-8 keys, e.g. `A = 0x4`
+    #[clap(long, value_parser)]
+    keymap: Option<String>,
+
+    /// Patch file of `ADDR BYTE` hex pairs applied to RAM after loading the ROM
+    #[clap(long, value_parser)]
+    patch: Option<String>,
+
+    /// Save the machine state to this file when quitting
+    #[clap(long, value_parser)]
+    save_on_quit: Option<PathBuf>,
+
+    /// Resume from a state file written by --save-on-quit
+    #[clap(long, value_parser)]
+    resume: Option<PathBuf>,
+
+    /// End a frame early when the ROM is busy-waiting on the delay timer
+    #[clap(long, value_parser)]
+    skip_delay_spin: bool,
+
+    /// Keep running while the window is in the background
+    #[clap(long, value_parser)]
+    no_pause_on_blur: bool,
+
+    /// Pause and show the debugger the first time the PC reaches this hex address
+    #[clap(long, value_parser = parse_hex_addr)]
+    break_at: Option<u16>,
+
+    /// Quirk preset to run with: chip8 (COSMAC VIP), schip or xochip
+    #[clap(long, value_parser = parse_compat)]
+    compat: Option<QuirkConfig>,
+
+    /// Run each frame for as long as a COSMAC VIP would have, instead of a fixed instruction count
+    #[clap(long, value_parser)]
+    vip_timing: bool,
+
+    /// Show which CHIP-8 key each held host key maps to instead of running the ROM
+    #[clap(long, value_parser)]
+    key_test: bool,
+
+    /// Warn when a single frame runs more than this many instructions
+    #[clap(long, value_parser, default_value_t = 1000)]
+    runaway_threshold: u64,
+
+    /// Pause instead of just warning when --runaway-threshold is exceeded
+    #[clap(long, value_parser)]
+    pause_on_runaway: bool,
+
+    /// Pause on unknown opcodes and out-of-RAM memory accesses instead of skipping or wrapping
+    #[clap(long, value_parser)]
+    strict: bool,
+
+    /// Run an untrusted ROM with every safeguard on, pausing on any fault instead of crashing
+    #[clap(long, value_parser)]
+    safe: bool,
+
+    /// Replay a recorded input demo without opening a window and print the final screen hash
+    #[clap(long, value_parser)]
+    demo: Option<String>,
+
+    /// With --demo, exit with an error unless the final screen hash matches this hex value
+    #[clap(long, value_parser, requires = "demo")]
+    expect_hash: Option<String>,
+
+    /// Print a disassembly of the ROM instead of running it
+    #[clap(long, value_parser)]
+    disasm: bool,
 }
 
-fn draw_screen(emu: &Emulator, scale: u32, canvas: &mut Canvas<Window>) {
-    canvas.set_draw_color(BLACK);
-    canvas.clear();
+// Parses a `--compat` preset name into its quirk configuration
+fn parse_compat(name: &str) -> Result<QuirkConfig, String> {
+    match name {
+        "chip8" => Ok(QuirkConfig::cosmac_vip()),
+        "schip" => Ok(QuirkConfig::super_chip()),
+        "xochip" => Ok(QuirkConfig::xo_chip()),
+        _ => Err("expected one of: chip8, schip, xochip".to_string()),
+    }
+}
 
-    let screen_buf = emu.get_display();
+// Parses a `--break-at` RAM address given in hex, with or without a `0x` prefix
+fn parse_hex_addr(s: &str) -> Result<u16, String> {
+    let hex = s.strip_prefix("0x").unwrap_or(s);
 
-    canvas.set_draw_color(WHITE);
+    match u16::from_str_radix(hex, 16) {
+        Ok(addr) if (addr as usize) < RAM_SIZE => Ok(addr),
+        _ => Err(format!("expected a hex address below 0x1000, got '{}'", s)),
+    }
+}
+
+/// Streaming textures for each display resolution, so switching between
+/// them never reallocates.
+struct ScreenTextures<'a> {
+    lores: Texture<'a>,
+    hires: Texture<'a>,
+    // Background and foreground colours last uploaded
+    colors: Option<([u8; 4], [u8; 4])>,
+}
 
-    for (i, pixel) in screen_buf.iter().enumerate() {
-        if *pixel {
-            let x = (i % SCREEN_WIDTH) as u32;
-            let y = (i / SCREEN_WIDTH) as u32;
-            let rect = Rect::new((x * scale) as i32, (y * scale) as i32, scale, scale);
+impl<'a> ScreenTextures<'a> {
+    fn new(creator: &'a TextureCreator<WindowContext>) -> Self {
+        let create = |width: usize, height: usize| {
+            creator
+                .create_texture_streaming(PixelFormatEnum::RGBA32, width as u32, height as u32)
+                .unwrap()
+        };
 
-            canvas.fill_rect(rect).unwrap();
+        Self {
+            lores: create(SCREEN_WIDTH, SCREEN_HEIGHT),
+            hires: create(HIRES_WIDTH, HIRES_HEIGHT),
+            colors: None,
         }
     }
+}
 
-    canvas.present();
+fn rgba(color: Color) -> [u8; 4] {
+    [color.r, color.g, color.b, 0xFF]
+}
+
+fn draw_screen(
+    emu: &Emulator,
+    palette: &Palette,
+    tint: Option<Color>,
+    dirty: bool,
+    textures: &mut ScreenTextures,
+    canvas: &mut Canvas<Window>,
+) {
+    let (width, _) = emu.display_size();
+    let texture = if width == HIRES_WIDTH {
+        &mut textures.hires
+    } else {
+        &mut textures.lores
+    };
+    let bg = rgba(palette.color(0));
+    let fg = rgba(palette.color(1));
+
+    // One texel per CHIP-8 pixel, scaled up to the window by a single copy.
+    // The texture only needs refilling when the screen or palette changed,
+    // but the frame is still presented every time since vsync paces the loop.
+    if dirty || textures.colors != Some((bg, fg)) {
+        texture
+            .update(None, &emu.framebuffer_rgba(fg, bg), width * 4)
+            .unwrap();
+
+        textures.colors = Some((bg, fg));
+    }
+
+    canvas.copy(texture, None, None).unwrap();
+
+    if let Some(color) = tint {
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(color);
+        canvas.fill_rect(None).unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+    }
+}
+
+fn dump_ram(emu: &Emulator) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = format!("ram-{}.bin", timestamp);
+    let ram = emu.mem_slice(0, RAM_SIZE).unwrap();
+
+    match fs::write(&path, ram) {
+        Ok(()) => println!("Dumped RAM to {}", path),
+        Err(err) => eprintln!("Failed to dump RAM to {}: {}", path, err),
+    }
 }
 
-fn get_keycode(key: Keycode) -> Option<usize> {
+fn poll_keypad(event_pump: &mut EventPump, keymap: &Keymap) -> [bool; 16] {
+    event_pump.pump_events();
+
+    let mut keys = [false; 16];
+
+    for scancode in event_pump.keyboard_state().pressed_scancodes() {
+        if let Some(k) = Keycode::from_scancode(scancode).and_then(|key| keymap.get(key)) {
+            keys[k] = true;
+        }
+    }
+
+    keys
+}
+
+fn get_digit(key: Keycode) -> Option<usize> {
     match key {
-        Keycode::Num1 => Some(0x1),
-        Keycode::Num2 => Some(0x2),
-        Keycode::Num3 => Some(0x3),
-        Keycode::Num4 => Some(0xC),
-        Keycode::Q => Some(0x4),
-        Keycode::W => Some(0x5),
-        Keycode::E => Some(0x6),
-        Keycode::R => Some(0xD),
-        Keycode::A => Some(0x7),
-        Keycode::S => Some(0x8),
-        Keycode::D => Some(0x9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0x0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
+        Keycode::Num1 => Some(1),
+        Keycode::Num2 => Some(2),
+        Keycode::Num3 => Some(3),
+        Keycode::Num4 => Some(4),
+        Keycode::Num5 => Some(5),
+        Keycode::Num6 => Some(6),
+        Keycode::Num7 => Some(7),
+        Keycode::Num8 => Some(8),
+        Keycode::Num9 => Some(9),
         _ => None,
     }
 }
 
+// One `ADDR: OPCODE  MNEMONIC` line per word from 0x200, with a trailing odd
+// byte shown as data
+fn print_disassembly(rom: &[u8]) {
+    for (i, word) in rom.chunks(2).enumerate() {
+        let addr = 0x200 + i * 2;
+
+        match *word {
+            [hi, lo] => {
+                let op = ((hi as u16) << 8) | lo as u16;
+                println!("0x{:03X}: {:04X}  {}", addr, op, disasm::disassemble(op));
+            }
+            [byte] => println!("0x{:03X}: {:02X}    DB 0x{:02X}", addr, byte, byte),
+            _ => unreachable!(),
+        }
+    }
+}
+
+// A colour from the per-ROM settings file, skipping it with a warning if the
+// file was edited into something unreadable
+fn saved_color(hex: &Option<String>, name: &str) -> Option<Color> {
+    parse_hex_color(hex.as_deref()?)
+        .map_err(|err| eprintln!("Ignoring saved {} colour: {}", name, err))
+        .ok()
+}
+
+// Headless: no window, no audio, just the demo's input and the final hash
+fn replay_demo(path: &str, rom: &[u8], ticks_per_frame: usize, expect_hash: Option<&str>) {
+    let result = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| Demo::parse(&text))
+        .and_then(|demo| {
+            demo.replay(rom, ticks_per_frame)
+                .map_err(|err| err.to_string())
+        });
+
+    let hash = match result {
+        Ok(emu) => format!("{:016x}", emu.screen_hash()),
+        Err(err) => {
+            eprintln!("Demo {} failed: {}", path, err);
+            std::process::exit(1);
+        }
+    };
+
+    println!("{}", hash);
+
+    if let Some(expected) = expect_hash {
+        if !hash.eq_ignore_ascii_case(expected.trim_start_matches("0x")) {
+            eprintln!("Screen hash mismatch: expected {}", expected);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn main() {
     let args = Args::parse();
 
-    let scaled_width = (SCREEN_WIDTH as u32) * args.scale;
-    let scaled_height = (SCREEN_HEIGHT as u32) * args.scale;
+    let mut rom = File::open(&args.path).unwrap();
+    let mut buffer = Vec::new();
+
+    rom.read_to_end(&mut buffer).unwrap();
+
+    if args.disasm {
+        print_disassembly(&buffer);
+        return;
+    }
+
+    if let Some(path) = &args.demo {
+        replay_demo(
+            path,
+            &buffer,
+            args.ticks_per_frame,
+            args.expect_hash.as_deref(),
+        );
+        return;
+    }
+
+    let keymap = match &args.keymap {
+        Some(path) => Keymap::load(path).unwrap_or_else(|err| {
+            eprintln!("Failed to load keymap {}: {}", path, err);
+            std::process::exit(1);
+        }),
+        None => Keymap::default(),
+    };
+
+    let rom_hash = rom_hash(&buffer);
+    let settings = RomSettings::load(rom_hash);
+
+    let scale = args.scale.or(settings.scale).unwrap_or(DEFAULT_SCALE);
+    let mut palette_idx = args
+        .palette
+        .as_deref()
+        .or(settings.palette.as_deref())
+        .and_then(palette::find)
+        .unwrap_or(0);
+    // Editable copies, so colour changes from the console stick until quitting
+    let mut palettes = PRESETS;
+
+    if let Some(color) = args.bg.or_else(|| saved_color(&settings.bg, "bg")) {
+        palettes[palette_idx].colors[0] = color;
+    }
+
+    if let Some(color) = args.fg.or_else(|| saved_color(&settings.fg, "fg")) {
+        palettes[palette_idx].colors[1] = color;
+    }
+
+    let mut chip8 = Emulator::new();
+
+    let (width, height) = chip8.display_size();
+    let scaled_width = width as u32 * scale;
+    let scaled_height = height as u32 * scale;
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
 
+    let rom_name = Path::new(&args.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| args.path.clone());
+    let title = format!("Chip-8 Emulator — {}", rom_name);
+
     let window = video_subsystem
-        .window("Chip-8 Emulator", scaled_width, scaled_height)
+        .window(&title, scaled_width, scaled_height)
         .position_centered()
         .opengl()
         .build()
@@ -90,13 +455,121 @@ fn main() {
     canvas.present();
 
     let mut event_pump = sdl_context.event_pump().unwrap();
-    let mut chip8 = Emulator::new();
+    let texture_creator = canvas.texture_creator();
+    let mut screen_textures = ScreenTextures::new(&texture_creator);
 
-    let mut rom = File::open(&args.path).unwrap();
-    let mut buffer = Vec::new();
+    if args.key_test {
+        keytest::run(
+            &mut canvas,
+            &mut event_pump,
+            &keymap,
+            &palettes[palette_idx],
+        );
+        return;
+    }
 
-    rom.read_to_end(&mut buffer).unwrap();
-    chip8.load(&buffer);
+    let audio_subsystem = sdl_context.audio().unwrap();
+    let audio_spec = AudioSpecDesired {
+        freq: Some(SAMPLE_RATE),
+        channels: Some(1),
+        samples: None,
+    };
+    let audio_queue: AudioQueue<i16> = audio_subsystem.open_queue(None, &audio_spec).unwrap();
+    let mut beeper = Beeper::new(
+        audio_queue.spec().freq as u32,
+        args.audio_slices,
+        args.beep_hz,
+        args.volume,
+    );
+
+    audio_queue.resume();
+
+    let variant = detect_variant(&buffer);
+    let detected_quirks = match variant {
+        Chip8Variant::Classic => None,
+        Chip8Variant::SuperChip => Some(QuirkConfig::super_chip()),
+        Chip8Variant::XoChip => Some(QuirkConfig::xo_chip()),
+    };
+
+    if variant != Chip8Variant::Classic {
+        println!(
+            "Warning: ROM looks like {:?}, which is not fully supported",
+            variant
+        );
+    }
+
+    for warning in diagnose::rom_warnings(&buffer) {
+        println!("Warning: {}", warning);
+    }
+
+    if let Err(err) = chip8.load(&buffer) {
+        eprintln!("Failed to load {}: {}", args.path, err);
+        std::process::exit(1);
+    }
+
+    let strict = args.strict || args.safe;
+
+    if strict {
+        chip8.set_unknown_opcode_policy(UnknownOpcodePolicy::Error);
+        chip8.set_strict(true);
+    } else {
+        chip8.set_unknown_opcode_policy(UnknownOpcodePolicy::Nop);
+    }
+
+    // An explicit preset beats whatever was saved for this ROM, which in turn
+    // beats the preset for the dialect its opcodes suggest
+    if let Some(quirks) = args.compat.or(settings.quirks) {
+        chip8.set_quirks(quirks);
+    } else if let Some(quirks) = detected_quirks {
+        println!("Using {:?} quirks; pass --compat to override", variant);
+        chip8.set_quirks(quirks);
+    }
+
+    // Kept so a reset can put the patch back over the freshly loaded ROM
+    let mut rom_patch = Vec::new();
+
+    if let Some(path) = &args.patch {
+        let result = patch::load_patch(path).and_then(|patch| {
+            chip8.apply_patch(&patch).map_err(|err| err.to_string())?;
+            rom_patch = patch;
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            eprintln!("Failed to apply patch: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(path) = &args.resume {
+        match savestate::load(path) {
+            Ok(state) => chip8.load_state(state),
+            Err(err) => eprintln!(
+                "Failed to resume from {}, starting fresh: {}",
+                path.display(),
+                err
+            ),
+        }
+    }
+
+    if let Some(addr) = args.break_at {
+        chip8.add_breakpoint(addr);
+    }
+
+    let mut paused = chip8.at_breakpoint();
+
+    if paused {
+        chip8.clear_breakpoints();
+        debugger::print_state(&chip8);
+    }
+    let mut blurred = false;
+    let mut show_memory = false;
+    let mut stats = FrameStats::new(chip8.cycle_count());
+    let mut vip_overshoot = 0;
+    let console = Console::spawn();
+    let mut runaway_warned = false;
+    let mut skipped_opcodes = BTreeSet::new();
+    let quicksave_path = Path::new(&args.path).with_extension("state");
 
     'gameloop: loop {
         for evt in event_pump.poll_iter() {
@@ -106,17 +579,113 @@ fn main() {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => break 'gameloop,
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } if !args.no_pause_on_blur => blurred = true,
+                Event::Window {
+                    win_event: WindowEvent::FocusGained,
+                    ..
+                } => blurred = false,
+                Event::KeyDown {
+                    keycode: Some(Keycode::Space),
+                    repeat: false,
+                    ..
+                } => {
+                    paused = !paused;
+
+                    if paused {
+                        debugger::print_state(&chip8);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(key @ (Keycode::LeftBracket | Keycode::RightBracket)),
+                    repeat: false,
+                    ..
+                } => {
+                    palette_idx = if key == Keycode::LeftBracket {
+                        (palette_idx + palettes.len() - 1) % palettes.len()
+                    } else {
+                        (palette_idx + 1) % palettes.len()
+                    };
+
+                    println!("Palette: {}", palettes[palette_idx].name);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F1),
+                    repeat: false,
+                    ..
+                } => {
+                    // `load` resets the machine first, and both calls already
+                    // succeeded at startup, so they can't fail now
+                    chip8.load(&buffer).unwrap();
+                    chip8.apply_patch(&rom_patch).unwrap();
+                    println!("Reset {}", rom_name);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    repeat: false,
+                    ..
+                } => show_memory = !show_memory,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    repeat: false,
+                    ..
+                } => match savestate::save(&quicksave_path, &chip8.save_state()) {
+                    Ok(()) => println!("Saved state to {}", quicksave_path.display()),
+                    Err(err) => eprintln!("Warning: quicksave failed: {}", err),
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => match savestate::load(&quicksave_path) {
+                    Ok(state) => {
+                        chip8.load_state(state);
+                        println!("Loaded state from {}", quicksave_path.display());
+                    }
+                    Err(err) => eprintln!(
+                        "Warning: couldn't load {}: {}",
+                        quicksave_path.display(),
+                        err
+                    ),
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => dump_ram(&chip8),
+                Event::KeyDown {
+                    keycode: Some(key),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if args.debug && keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    if let Some(n) = get_digit(key) {
+                        debugger::toggle_quirk(&mut chip8, n);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::N),
+                    ..
+                } if paused => {
+                    if let Err(err) = chip8.tick() {
+                        eprintln!("{}", err);
+                    }
+
+                    debugger::print_state(&chip8);
+                }
                 Event::KeyDown {
                     keycode: Some(key), ..
                 } => {
-                    if let Some(k) = get_keycode(key) {
+                    if let Some(k) = keymap.get(key) {
                         chip8.keypress(k, true)
                     }
                 }
                 Event::KeyUp {
                     keycode: Some(key), ..
                 } => {
-                    if let Some(k) = get_keycode(key) {
+                    if let Some(k) = keymap.get(key) {
                         chip8.keypress(k, false)
                     }
                 }
@@ -124,12 +693,197 @@ fn main() {
             }
         }
 
-        for _ in 0..TICKS_PER_FRAME {
-            chip8.tick();
+        for command in console.pending() {
+            if paused {
+                debugger::run_command(&mut chip8, &mut palettes[palette_idx], &command);
+            } else {
+                println!("Pause with Space before entering debugger commands");
+            }
+        }
+
+        if !paused && !blurred {
+            // Cycles run past last frame's budget come out of this one
+            let vip_frame_start = chip8.vip_cycles();
+            let vip_budget = VIP_CYCLES_PER_FRAME.saturating_sub(vip_overshoot);
+            let vip_frame_end = vip_frame_start + vip_budget;
+            let frame_start_cycles = chip8.cycle_count();
+            let slices = args.audio_slices as usize;
+            let mut slice = 0;
+            let mut ticks = 0;
+
+            let mut queue_slice = |chip8: &Emulator| {
+                let samples = if chip8.is_sound_active() {
+                    beeper.slice()
+                } else {
+                    beeper.stop()
+                };
+
+                if !samples.is_empty() {
+                    audio_queue.queue_audio(&samples).unwrap();
+                }
+            };
+
+            loop {
+                // Queue each slice's audio as soon as its instructions have
+                // run, so the tone follows the sound timer within a slice
+                while slice < slices {
+                    let slice_done = if args.vip_timing {
+                        let end = vip_budget * (slice as u64 + 1) / slices as u64;
+                        chip8.vip_cycles() >= vip_frame_start + end
+                    } else {
+                        ticks >= args.ticks_per_frame * (slice + 1) / slices
+                    };
+
+                    if !slice_done {
+                        break;
+                    }
+
+                    queue_slice(&chip8);
+                    slice += 1;
+                }
+
+                // After 00FD the last frame stays on screen until F1 or quit
+                if slice == slices || chip8.is_halted() {
+                    break;
+                }
+
+                ticks += 1;
+
+                let was_beeping = chip8.is_sound_active();
+
+                let result = if args.low_latency_input {
+                    chip8.tick_with_input(|| poll_keypad(&mut event_pump, &keymap))
+                } else {
+                    chip8.tick()
+                };
+
+                if let Err(err) = result {
+                    eprintln!("{}", err);
+                    debugger::print_state(&chip8);
+                    paused = true;
+                    break;
+                }
+
+                if chip8.is_halted() {
+                    println!("Program exited (press F1 to restart)");
+                    break;
+                }
+
+                if args.break_on_beep && !was_beeping && chip8.is_sound_active() {
+                    println!("Beep started, pausing (press Space to resume)");
+                    debugger::print_state(&chip8);
+                    paused = true;
+                    break;
+                }
+
+                if chip8.at_breakpoint() {
+                    println!("Breakpoint at 0x{:03X}", chip8.program_counter());
+                    chip8.clear_breakpoints();
+                    debugger::print_state(&chip8);
+                    paused = true;
+                    break;
+                }
+
+                if args.skip_delay_spin && chip8.detect_delay_spin() {
+                    break;
+                }
+            }
+
+            vip_overshoot = chip8.vip_cycles().saturating_sub(vip_frame_end);
+
+            let frame_cycles = chip8.cycle_count() - frame_start_cycles;
+
+            // Outside strict mode unknown opcodes are skipped, so mention each once
+            if !strict {
+                for (op, _) in chip8.unknown_opcodes() {
+                    if skipped_opcodes.insert(op) {
+                        println!("Warning: skipped unknown opcode {:04X}", op);
+                    }
+                }
+            }
+
+            // Warn once per runaway stretch rather than on every frame of it
+            if frame_cycles > args.runaway_threshold {
+                if !runaway_warned {
+                    println!(
+                        "Warning: {} instructions in one frame near 0x{:03X}, the ROM may be stuck in a loop",
+                        frame_cycles,
+                        chip8.program_counter()
+                    );
+                    runaway_warned = true;
+                }
+
+                if (args.pause_on_runaway || args.safe) && !paused {
+                    debugger::print_state(&chip8);
+                    paused = true;
+                }
+            } else {
+                runaway_warned = false;
+            }
+
+            if !paused {
+                // A frame cut short by --skip-delay-spin still lasts a whole frame
+                while slice < slices {
+                    queue_slice(&chip8);
+                    slice += 1;
+                }
+
+                chip8.tick_timers();
+            }
         }
 
-        chip8.tick_timers();
-        draw_screen(&chip8, args.scale, &mut canvas)
+        let tint = if args.visual_beep && chip8.is_sound_active() {
+            let Color { r, g, b, .. } = args.visual_beep_color;
+            Some(Color::RGBA(r, g, b, args.visual_beep_alpha))
+        } else {
+            None
+        };
+
+        let dirty = chip8.take_display_dirty();
+
+        draw_screen(
+            &chip8,
+            &palettes[palette_idx],
+            tint,
+            dirty,
+            &mut screen_textures,
+            &mut canvas,
+        );
+
+        if show_memory {
+            memview::draw_memory_map(&chip8, scale, &mut canvas);
+        }
+
+        canvas.present();
+
+        if let Some((fps, ips)) = stats.frame(chip8.cycle_count()) {
+            if args.show_stats {
+                let stats_title = format!("{} — {} FPS — {} IPS", title, fps, ips);
+                canvas.window_mut().set_title(&stats_title).unwrap();
+            }
+        }
+    }
+
+    if let Some(path) = &args.save_on_quit {
+        match savestate::save(path, &chip8.save_state()) {
+            Ok(()) => println!("Saved state to {}", path.display()),
+            Err(err) => eprintln!("Failed to save state to {}: {}", path.display(), err),
+        }
+    }
+
+    if args.save_settings {
+        let settings = RomSettings {
+            scale: Some(scale),
+            palette: Some(palettes[palette_idx].name.to_string()),
+            fg: Some(format_hex_color(palettes[palette_idx].fg())),
+            bg: Some(format_hex_color(palettes[palette_idx].bg())),
+            quirks: Some(chip8.quirks()),
+        };
+
+        match settings.save(rom_hash) {
+            Ok(path) => println!("Saved settings to {}", path.display()),
+            Err(err) => eprintln!("Failed to save settings: {}", err),
+        }
     }
 
     println!("Hello, {:?}!", args);
@@ -0,0 +1,26 @@
+use chip8_core::{Emulator, RAM_SIZE};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+const MAP_WIDTH: usize = 64;
+
+/// Draws RAM as a 64x64 grayscale heatmap (one cell per byte, brighter means
+/// a larger value) over the right half of the window. Data tables, the font
+/// and code being rewritten at runtime all stand out clearly.
+pub fn draw_memory_map(emu: &Emulator, scale: u32, canvas: &mut Canvas<Window>) {
+    let cell = (scale / 2).max(1);
+    let (width, _) = canvas.output_size().unwrap();
+    let origin_x = width.saturating_sub(cell * MAP_WIDTH as u32) as i32;
+    let ram = emu.mem_slice(0, RAM_SIZE).unwrap();
+
+    for (addr, byte) in ram.iter().enumerate() {
+        let x = (addr % MAP_WIDTH) as u32;
+        let y = (addr / MAP_WIDTH) as u32;
+        let rect = Rect::new(origin_x + (x * cell) as i32, (y * cell) as i32, cell, cell);
+
+        canvas.set_draw_color(Color::RGB(*byte, *byte, *byte));
+        canvas.fill_rect(rect).unwrap();
+    }
+}
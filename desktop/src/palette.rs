@@ -0,0 +1,140 @@
+use sdl2::pixels::Color;
+
+/// Colours indexed by which XO-CHIP planes a pixel is lit on: 0 is the
+/// background, 1 the first plane (the only one classic CHIP-8 uses), 2 the
+/// second plane and 3 both.
+#[derive(Clone, Copy)]
+pub struct Palette {
+    pub name: &'static str,
+    pub colors: [Color; 4],
+}
+
+impl Palette {
+    pub fn bg(&self) -> Color {
+        self.colors[0]
+    }
+
+    pub fn fg(&self) -> Color {
+        self.colors[1]
+    }
+
+    /// Colour for a pixel with the given plane bits (only the low two count).
+    pub fn color(&self, planes: u8) -> Color {
+        self.colors[(planes & 0b11) as usize]
+    }
+}
+
+pub const PRESETS: [Palette; 5] = [
+    Palette {
+        name: "classic",
+        colors: [
+            Color::RGB(0, 0, 0),
+            Color::RGB(255, 255, 255),
+            Color::RGB(255, 102, 0),
+            Color::RGB(102, 34, 0),
+        ],
+    },
+    Palette {
+        name: "amber",
+        colors: [
+            Color::RGB(40, 24, 0),
+            Color::RGB(255, 176, 0),
+            Color::RGB(176, 96, 0),
+            Color::RGB(255, 232, 160),
+        ],
+    },
+    Palette {
+        name: "green",
+        colors: [
+            Color::RGB(0, 32, 8),
+            Color::RGB(51, 255, 102),
+            Color::RGB(24, 128, 48),
+            Color::RGB(192, 255, 208),
+        ],
+    },
+    Palette {
+        name: "lcd",
+        colors: [
+            Color::RGB(155, 188, 15),
+            Color::RGB(15, 56, 15),
+            Color::RGB(139, 172, 15),
+            Color::RGB(48, 98, 48),
+        ],
+    },
+    Palette {
+        name: "inverted",
+        colors: [
+            Color::RGB(255, 255, 255),
+            Color::RGB(0, 0, 0),
+            Color::RGB(0, 153, 255),
+            Color::RGB(0, 68, 153),
+        ],
+    },
+];
+
+pub fn find(name: &str) -> Option<usize> {
+    PRESETS.iter().position(|palette| palette.name == name)
+}
+
+pub fn parse_palette_name(name: &str) -> Result<String, String> {
+    match find(name) {
+        Some(_) => Ok(name.to_string()),
+        None => {
+            let names: Vec<&str> = PRESETS.iter().map(|palette| palette.name).collect();
+            Err(format!("expected one of: {}", names.join(", ")))
+        }
+    }
+}
+
+/// Parses an `RRGGBB` hex string (an optional leading `#` is allowed).
+pub fn parse_hex_color(s: &str) -> Result<Color, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+
+    if hex.len() != 6 {
+        return Err(format!("expected a RRGGBB hex colour, got '{}'", s));
+    }
+
+    let rgb = u32::from_str_radix(hex, 16)
+        .map_err(|_| format!("expected a RRGGBB hex colour, got '{}'", s))?;
+
+    Ok(Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+/// Formats a colour as `RRGGBB` hex, the form `parse_hex_color` reads.
+pub fn format_hex_color(color: Color) -> String {
+    format!("{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plane_bits_pick_the_configured_colours() {
+        let palette = Palette {
+            name: "test",
+            colors: [
+                Color::RGB(1, 1, 1),
+                Color::RGB(2, 2, 2),
+                Color::RGB(3, 3, 3),
+                Color::RGB(4, 4, 4),
+            ],
+        };
+
+        assert_eq!(palette.color(0b00), palette.bg());
+        assert_eq!(palette.color(0b01), palette.fg());
+        assert_eq!(palette.color(0b10), Color::RGB(3, 3, 3));
+        assert_eq!(palette.color(0b11), Color::RGB(4, 4, 4));
+        assert_eq!(palette.color(0b110), Color::RGB(3, 3, 3));
+        assert_eq!(palette.bg(), Color::RGB(1, 1, 1));
+        assert_eq!(palette.fg(), Color::RGB(2, 2, 2));
+    }
+
+    #[test]
+    fn hex_colours_round_trip() {
+        let color = Color::RGB(0x33, 0xFF, 0x06);
+
+        assert_eq!(format_hex_color(color), "33FF06");
+        assert_eq!(parse_hex_color(&format_hex_color(color)), Ok(color));
+    }
+}
@@ -0,0 +1,39 @@
+use std::fs;
+
+fn parse_hex(s: &str) -> Result<u32, String> {
+    let digits = s.trim_start_matches("0x").trim_start_matches("0X");
+    u32::from_str_radix(digits, 16).map_err(|_| format!("'{}' is not a hex number", s))
+}
+
+/// Reads a patch file: one `ADDR BYTE` pair of hex numbers per line, with
+/// blank lines and `#` comments ignored.
+pub fn load_patch(path: &str) -> Result<Vec<(u16, u8)>, String> {
+    let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    let mut patch = Vec::new();
+
+    for (line_no, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let entry = match fields[..] {
+            [addr, val] => parse_hex(addr).and_then(|addr| {
+                let val = parse_hex(val)?;
+
+                if addr > u16::MAX as u32 || val > u8::MAX as u32 {
+                    return Err("value out of range".to_string());
+                }
+
+                Ok((addr as u16, val as u8))
+            }),
+            _ => Err("expected 'ADDR BYTE'".to_string()),
+        };
+
+        patch.push(entry.map_err(|err| format!("{}:{}: {}", path, line_no + 1, err))?);
+    }
+
+    Ok(patch)
+}
@@ -0,0 +1,52 @@
+use chip8_core::SaveState;
+use std::fs;
+use std::path::Path;
+
+/// Writes `state` to `path` in bincode.
+pub fn save(path: &Path, state: &SaveState) -> Result<(), String> {
+    let bytes = bincode::serialize(state).map_err(|err| err.to_string())?;
+
+    fs::write(path, bytes).map_err(|err| err.to_string())
+}
+
+pub fn load(path: &Path) -> Result<SaveState, String> {
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+
+    bincode::deserialize(&bytes).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::Emulator;
+    use std::env;
+
+    #[test]
+    fn state_survives_save_on_quit_and_resume() {
+        let path = env::temp_dir().join(format!("rust-chip-8-quit-{}.state", std::process::id()));
+        let mut emu = Emulator::new();
+        // LD V0, 7; LD ST, V0; LD I, 0x300; LD F, V0; DRW V1, V2, 5
+        emu.load(&[0x60, 0x07, 0xF0, 0x18, 0xA3, 0x00, 0xF0, 0x29, 0xD1, 0x25])
+            .unwrap();
+        emu.run_cycles(5).unwrap();
+
+        save(&path, &emu.save_state()).unwrap();
+        let mut resumed = Emulator::new();
+        resumed.load_state(load(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(resumed.state(), emu.state());
+        assert_eq!(resumed.save_state(), emu.save_state());
+    }
+
+    #[test]
+    fn resume_reports_bad_files() {
+        let path = env::temp_dir().join(format!("rust-chip-8-bad-{}.state", std::process::id()));
+
+        assert!(load(&path).is_err());
+
+        fs::write(&path, b"not a save state").unwrap();
+        assert!(load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+use chip8_core::QuirkConfig;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Settings remembered for a single ROM, keyed on disk by the ROM's hash.
+/// Anything left as `None` falls back to the usual default, and CLI flags
+/// always win over what is stored here. Colours are `RRGGBB` hex strings.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RomSettings {
+    pub scale: Option<u32>,
+    pub palette: Option<String>,
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    pub quirks: Option<QuirkConfig>,
+}
+
+fn settings_path(rom_hash: u64) -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "", "rust-chip-8")?;
+    let file_name = format!("{:016x}.toml", rom_hash);
+
+    Some(dirs.config_dir().join("roms").join(file_name))
+}
+
+impl RomSettings {
+    /// Loads the stored settings for a ROM. A missing file just means nothing
+    /// has been saved yet; an unreadable one is reported and ignored.
+    pub fn load(rom_hash: u64) -> RomSettings {
+        match settings_path(rom_hash) {
+            Some(path) => RomSettings::load_from(&path),
+            None => RomSettings::default(),
+        }
+    }
+
+    fn load_from(path: &Path) -> RomSettings {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return RomSettings::default(),
+        };
+
+        toml::from_str(&contents).unwrap_or_else(|err| {
+            eprintln!("Ignoring invalid settings in {}: {}", path.display(), err);
+            RomSettings::default()
+        })
+    }
+
+    pub fn save(&self, rom_hash: u64) -> Result<PathBuf, String> {
+        let path = settings_path(rom_hash).ok_or("no config directory available")?;
+
+        self.save_to(&path)?;
+        Ok(path)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), String> {
+        let contents = toml::to_string(self).map_err(|err| err.to_string())?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+
+        fs::write(path, contents).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::{DisplayWait, DrawMode, MemoryIncrement};
+    use std::env;
+
+    #[test]
+    fn settings_round_trip() {
+        let dir = env::temp_dir().join(format!("rust-chip-8-settings-{}", std::process::id()));
+        let path = dir.join("roms").join("0123456789abcdef.toml");
+        let settings = RomSettings {
+            scale: Some(15),
+            palette: Some("amber".to_string()),
+            fg: None,
+            bg: None,
+            quirks: None,
+        };
+
+        assert_eq!(RomSettings::load_from(&path), RomSettings::default());
+
+        settings.save_to(&path).unwrap();
+        assert_eq!(RomSettings::load_from(&path), settings);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn colours_and_every_quirk_round_trip() {
+        let dir = env::temp_dir().join(format!("rust-chip-8-quirks-{}", std::process::id()));
+        let path = dir.join("roms").join("fedcba9876543210.toml");
+        let settings = RomSettings {
+            scale: None,
+            palette: Some("classic".to_string()),
+            fg: Some("33FF66".to_string()),
+            bg: Some("001020".to_string()),
+            quirks: Some(QuirkConfig {
+                memory_increment: MemoryIncrement::ByX,
+                display_wait: DisplayWait::PerFrame,
+                draw_mode: DrawMode::Or,
+                ..QuirkConfig::cosmac_vip()
+            }),
+        };
+
+        settings.save_to(&path).unwrap();
+        assert_eq!(RomSettings::load_from(&path), settings);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_quirks_take_their_defaults() {
+        let settings: RomSettings = toml::from_str("[quirks]\nshift_uses_vy = true\n").unwrap();
+
+        assert_eq!(
+            settings.quirks,
+            Some(QuirkConfig {
+                shift_uses_vy: true,
+                ..QuirkConfig::default()
+            })
+        );
+    }
+}
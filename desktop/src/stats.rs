@@ -0,0 +1,38 @@
+use std::time::{Duration, Instant};
+
+const SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+
+pub struct FrameStats {
+    started: Instant,
+    frames: u32,
+    start_cycles: u64,
+}
+
+impl FrameStats {
+    pub fn new(cycles: u64) -> Self {
+        Self {
+            started: Instant::now(),
+            frames: 0,
+            start_cycles: cycles,
+        }
+    }
+
+    /// Records a presented frame and, once a full second has been sampled,
+    /// returns the measured `(fps, ips)` and starts a new sample.
+    pub fn frame(&mut self, cycles: u64) -> Option<(u32, u64)> {
+        self.frames += 1;
+
+        let elapsed = self.started.elapsed();
+
+        if elapsed < SAMPLE_PERIOD {
+            return None;
+        }
+
+        let secs = elapsed.as_secs_f64();
+        let fps = (self.frames as f64 / secs).round() as u32;
+        let ips = (cycles.saturating_sub(self.start_cycles) as f64 / secs).round() as u64;
+
+        *self = FrameStats::new(cycles);
+        Some((fps, ips))
+    }
+}